@@ -1,6 +1,7 @@
 pub use clap::Parser;
 use clap::Subcommand;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "crafty", bin_name = "crafty", author, version, about)]
@@ -22,11 +23,64 @@ pub enum Commands {
     Init,
     /// Install the files and plugins for a project
     Install {
+        /// Download every file again, even if it did not change
         #[arg(short, long)]
         force: bool,
+        /// Update the lockfile entries of the given dependencies (or `server`), updates all if no id is provided
+        #[arg(long, num_args = 0.., value_name = "ID", conflicts_with = "locked")]
+        update: Option<Vec<String>>,
+        /// Fail if the lockfile is missing or out of date
+        #[arg(long)]
+        locked: bool,
+        /// Maximum number of dependencies downloaded at the same time
+        #[arg(short, long, default_value_t = project::DEFAULT_JOBS)]
+        jobs: usize,
+        /// Only install files from the cache, without making network requests
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Add a dependency to the project and install it
+    Add {
+        /// Dependency id (looked up on Modrinth), URL, path or source (eg. `hangar:<owner>/<slug>`)
+        target: String,
+        #[arg(long)]
+        version: Option<String>,
+        #[arg(
+            long = "type",
+            value_parser = ["mod", "plugin", "data_pack", "library", "resource_pack", "custom"]
+        )]
+        dependency_type: Option<String>,
+        /// Directory to install the dependency in, relative to the server directory (for `custom` dependencies)
+        #[arg(long = "target", id = "target_directory", value_name = "DIRECTORY")]
+        target_directory: Option<String>,
+        /// Skip the dependency if it cannot be installed
+        #[arg(long)]
+        optional: bool,
+    },
+    /// Remove a dependency from the project
+    Remove {
+        id: String,
+    },
+    /// Show the latest versions of the server and dependencies
+    Outdated {
+        /// Print the versions as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Update the pinned versions of the given dependencies (or `server`), updates all if no id is provided
+    Update {
+        ids: Vec<String>,
+    },
+    /// Inspect the dependencies between plugins and mods
+    Deps {
+        #[command(subcommand)]
+        command: DepsCommand,
+    },
+    /// Manage the download cache shared by all projects
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
     },
-    /*/// Add a plugin to the project
-    Add { name: String },*/
     /// Run the server project
     Run {
         #[arg(long)]
@@ -35,3 +89,26 @@ pub enum Commands {
     /// Pack the server and its files
     Pack,
 }
+
+#[derive(Subcommand)]
+pub enum DepsCommand {
+    /// Print the dependency graph of the installed plugins and mods
+    Tree {
+        #[arg(long, default_value = "text", value_parser = ["text", "dot", "json"])]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// List the cached files
+    List,
+    /// Remove every cached file
+    Clean,
+    /// Remove the cached files that were not used recently
+    Prune {
+        /// Remove files not used for this long (eg. `12h`, `30d`, `2w`)
+        #[arg(long, default_value = "30d", value_parser = project::cache::parse_duration)]
+        older_than: Duration,
+    },
+}
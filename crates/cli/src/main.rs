@@ -3,7 +3,7 @@ mod runtime;
 
 use anyhow::Context;
 
-use cli::{Cli, Commands, Parser};
+use cli::{CacheCommand, Cli, Commands, DepsCommand, Parser};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -35,8 +35,49 @@ async fn main() -> anyhow::Result<()> {
         Commands::New { path } => templater::generate_template(current_directory.join(path))
             .context("Generating template"),
         Commands::Init => templater::generate_template(current_directory),
-        Commands::Install { force } => project::install(current_directory, force).await,
-        // Commands::Add { name } => project::add_dependency(current_directory, name).await,
+        Commands::Install {
+            force,
+            update,
+            locked,
+            jobs,
+            offline,
+        } => {
+            let options = project::InstallOptions {
+                force,
+                update,
+                locked,
+                jobs,
+                offline,
+            };
+            project::install(current_directory, options).await
+        }
+        Commands::Add {
+            target,
+            version,
+            dependency_type,
+            target_directory,
+            optional,
+        } => {
+            let options = project::AddOptions {
+                target,
+                version,
+                dependency_type,
+                target_directory,
+                optional,
+            };
+            project::add_dependency(current_directory, options).await
+        }
+        Commands::Remove { id } => project::remove_dependency(current_directory, id),
+        Commands::Outdated { json } => project::updates::outdated(current_directory, json).await,
+        Commands::Update { ids } => project::updates::update(current_directory, ids).await,
+        Commands::Deps { command } => match command {
+            DepsCommand::Tree { format } => project::graph::print_tree(current_directory, &format),
+        },
+        Commands::Cache { command } => match command {
+            CacheCommand::List => project::cache::print_list(),
+            CacheCommand::Clean => project::cache::clean(),
+            CacheCommand::Prune { older_than } => project::cache::prune(older_than),
+        },
         Commands::Run { no_setup } => {
             runtime::run_project(current_directory, profile_name, no_setup).await
         }
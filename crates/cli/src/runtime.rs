@@ -1,12 +1,11 @@
 use anyhow::Context;
 use project::manifests::{DependenciesManifest, VersionManifest};
 use project::settings::ProjectSettings;
-use project::{dependencies, load_project, process_files};
-use std::ffi::OsString;
+use project::{dependencies, java_binary, load_project, process_files};
+use std::fs;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::{env, fs};
 use tokio::process::Command;
 
 pub async fn run_project(
@@ -15,10 +14,12 @@ pub async fn run_project(
     no_setup: bool,
 ) -> anyhow::Result<()> {
     let project = load_project(&root_directory)?;
-    let settings = project.get_settings(profile_name).unwrap_or_else(|_| {
-        log::warn!("No settings file was found, using default values...");
-        ProjectSettings::default()
-    });
+    let settings = project
+        .get_settings(profile_name.clone())
+        .unwrap_or_else(|_| {
+            log::warn!("No settings file was found, using default values...");
+            ProjectSettings::default()
+        });
 
     let version = project
         .get_manifest::<VersionManifest>()
@@ -26,10 +27,12 @@ pub async fn run_project(
 
     let dependencies = project
         .get_manifest::<DependenciesManifest>()
-        .context("Dependency manifest was not found, make sure to run `crafty install` first")?;
+        .context("Dependency manifest was not found, make sure to run `crafty install` first")?
+        .for_profile(profile_name.as_deref());
 
     let server_directory = root_directory.join("server");
     fs::create_dir_all(&server_directory)?;
+    let libraries = dependencies.libraries();
 
     if no_setup {
         log::warn!("Skipping setup, this is only recommended when running the server for the first time...");
@@ -37,7 +40,20 @@ pub async fn run_project(
         prepare_files(&root_directory, &server_directory, dependencies, &settings)?;
     }
 
-    run_server(&server_directory, version.jar_file, settings).await
+    let server_jar = match &version.install_directory {
+        // Servers generated by an installer are launched from the server directory
+        Some(install_directory) => {
+            log::info!("Copying installed server files...");
+            common::utils::copy_directory(install_directory, &server_directory)
+                .context("Could not copy installed server files")?;
+
+            version.jar_file_name().to_string()
+        }
+        None => version.jar_file.clone(),
+    };
+    let launch_arguments = version.launch_arguments(&server_jar, &libraries, cfg!(windows))?;
+
+    run_server(&server_directory, launch_arguments, settings).await
 }
 
 fn prepare_files(
@@ -46,28 +62,20 @@ fn prepare_files(
     dependencies: DependenciesManifest,
     settings: &ProjectSettings,
 ) -> anyhow::Result<()> {
-    dependencies::prepare_server_dependencies(
-        dependencies,
-        &root_directory.join(".crafty").join("dependencies"),
-        &server_directory,
-    )?;
-
     // TODO: Refactor
-    process_files(root_directory, server_directory, settings.clone())
+    // Files are processed first, data packs are installed in the world set in `server.properties`
+    process_files(root_directory, server_directory, settings.clone())?;
+
+    dependencies::prepare_server_dependencies(dependencies, root_directory, server_directory)
 }
 
-async fn run_server<T: AsRef<Path>, U: AsRef<Path>>(
+async fn run_server<T: AsRef<Path>>(
     server_directory: T,
-    server_jar: U,
+    launch_arguments: Vec<String>,
     settings: ProjectSettings,
 ) -> anyhow::Result<()> {
     log::info!("Running server...");
-    let java: OsString = match (env::var("JAVA_BIN").ok(), env::var("JAVA_HOME").ok()) {
-        (Some(java_bin), _) => PathBuf::from(java_bin).into(),
-        (None, Some(java_home)) => PathBuf::from(java_home).join("bin/java").into(),
-        (None, None) => "java".into(),
-    };
-    let mut command = Command::new(java);
+    let mut command = Command::new(java_binary());
     command.kill_on_drop(true);
     command.current_dir(server_directory.as_ref());
 
@@ -75,8 +83,7 @@ async fn run_server<T: AsRef<Path>, U: AsRef<Path>>(
         command.arg(arg);
     }
 
-    command.arg("-jar");
-    command.arg(server_jar.as_ref());
+    command.args(launch_arguments);
 
     for arg in settings.server_args {
         command.arg(arg);
@@ -1,7 +1,7 @@
 use std::fs::File;
-use std::io;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::{fs, io};
 
 pub fn append_or_check_file<P: AsRef<Path>>(path: P, file_name: &str) -> Option<PathBuf> {
     let path = path.as_ref();
@@ -43,6 +43,25 @@ pub fn find_up_file(path: &Path, file_name: &str) -> Option<PathBuf> {
     }
 }
 
+/// Recursively copies the contents of a directory into another one, overwriting existing files
+pub fn copy_directory<P: AsRef<Path>, Q: AsRef<Path>>(source: P, target: Q) -> io::Result<()> {
+    let target = target.as_ref();
+    fs::create_dir_all(target)?;
+
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let destination = target.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_directory(entry.path(), destination)?;
+        } else {
+            fs::copy(entry.path(), destination)?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Detects if a file is most likely a binary based on the presence of null bytes and control characters.
 ///
 /// It reads the first few bytes of the file
@@ -0,0 +1,163 @@
+//! Extraction of archive dependencies (eg. a ZIP bundle with a plugin and its config folders)
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use glob::{MatchOptions, Pattern};
+use zip::ZipArchive;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Extracts the files of a ZIP archive into a directory and returns their paths relative to it.
+///
+/// The first `strip_components` directories of each path are removed (files with fewer are skipped),
+/// and only the files matching one of the `include` patterns are extracted if any is given
+pub(crate) fn extract(
+    archive_path: &Path,
+    destination: &Path,
+    include: &[String],
+    strip_components: usize,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let patterns = include
+        .iter()
+        .map(|pattern| {
+            Pattern::new(pattern)
+                .with_context(|| format!("Invalid include pattern \"{}\"", pattern))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut archive = ZipArchive::new(File::open(archive_path)?)
+        .with_context(|| format!("\"{}\" is not a ZIP file", archive_path.display()))?;
+    let mut extracted = vec![];
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if entry.is_dir() {
+            continue;
+        }
+
+        let name = entry.name().to_string();
+        let path = entry
+            .enclosed_name()
+            .ok_or_else(|| anyhow!("The archive contains an invalid path \"{}\"", name))?;
+        let relative_path: PathBuf = path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .skip(strip_components)
+            .collect();
+
+        let is_included = patterns.is_empty()
+            || patterns
+                .iter()
+                .any(|pattern| pattern.matches_path_with(&relative_path, MATCH_OPTIONS));
+        if relative_path.as_os_str().is_empty() || !is_included {
+            continue;
+        }
+
+        let target = destination.join(&relative_path);
+        fs::create_dir_all(target.parent().unwrap())?;
+        io::copy(&mut entry, &mut File::create(&target)?)
+            .with_context(|| format!("Could not extract \"{}\"", name))?;
+        extracted.push(relative_path);
+    }
+
+    if extracted.is_empty() {
+        return Err(anyhow!(
+            "No files were extracted from \"{}\", check the `include` and `strip-components` properties",
+            archive_path.display()
+        ));
+    }
+
+    extracted.sort();
+    Ok(extracted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    fn archive(directory: &Path, files: &[&str]) -> PathBuf {
+        let path = directory.join("archive.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        for file in files {
+            writer.start_file(*file, FileOptions::default()).unwrap();
+            writer.write_all(file.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        path
+    }
+
+    const FILES: &[&str] = &[
+        "bundle-1.0/plugin.jar",
+        "bundle-1.0/config/config.yml",
+        "bundle-1.0/config/lang/en.yml",
+        "bundle-1.0/README.md",
+    ];
+
+    #[test]
+    fn extracts_included_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let archive = archive(directory.path(), FILES);
+        let destination = directory.path().join("out");
+
+        let include = ["*.jar".to_string(), "config/**".to_string()];
+        let extracted = extract(&archive, &destination, &include, 1).unwrap();
+
+        assert_eq!(
+            extracted,
+            vec![
+                PathBuf::from("config/config.yml"),
+                PathBuf::from("config/lang/en.yml"),
+                PathBuf::from("plugin.jar"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(destination.join("plugin.jar")).unwrap(),
+            "bundle-1.0/plugin.jar"
+        );
+        assert!(!destination.join("README.md").exists());
+    }
+
+    #[test]
+    fn wildcards_do_not_match_nested_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let archive = archive(directory.path(), FILES);
+
+        let extracted = extract(&archive, &directory.path().join("out"), &["*".into()], 1).unwrap();
+        assert_eq!(
+            extracted,
+            vec![PathBuf::from("README.md"), PathBuf::from("plugin.jar")]
+        );
+    }
+
+    #[test]
+    fn fails_if_nothing_is_extracted() {
+        let directory = tempfile::tempdir().unwrap();
+        let archive = archive(directory.path(), FILES);
+
+        assert!(extract(&archive, &directory.path().join("out"), &[], 4).is_err());
+    }
+
+    #[test]
+    fn rejects_paths_outside_of_the_destination() {
+        let directory = tempfile::tempdir().unwrap();
+        let destination = directory.path().join("out");
+
+        for path in ["../evil.txt", "config/../../evil.txt", "/etc/evil.txt"] {
+            let archive = archive(directory.path(), &[path]);
+            assert!(extract(&archive, &destination, &[], 0).is_err(), "{}", path);
+            assert!(!directory.path().join("evil.txt").exists());
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Credentials sent when downloading from authenticated hosts (eg. a private Nexus or BuiltByBit)
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use base64::Engine;
+use glob::Pattern;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+
+use crate::installer::Downloader;
+
+/// Headers or basic authentication used for the hosts matching a pattern (eg. `*.example.com`),
+/// environment variables can be used in every value with `${VARIABLE}`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HostAuth {
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl HostAuth {
+    /// Builds the headers with the environment variables replaced, they are marked as sensitive
+    /// so their values are never shown in logs
+    fn to_headers(&self, host: &str) -> anyhow::Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name \"{}\" for \"{}\"", name, host))?;
+            let value = crate::interpolate_env(value).with_context(|| {
+                format!("Could not read the \"{}\" header for \"{}\"", name, host)
+            })?;
+            headers.insert(name.clone(), sensitive_value(&value, &name, host)?);
+        }
+
+        match (&self.username, &self.password) {
+            (Some(username), Some(password)) => {
+                let credentials = format!(
+                    "{}:{}",
+                    crate::interpolate_env(username)?,
+                    crate::interpolate_env(password)?
+                );
+                let value = format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(credentials)
+                );
+                headers.insert(
+                    AUTHORIZATION,
+                    sensitive_value(&value, &AUTHORIZATION, host)?,
+                );
+            }
+            (None, None) => {}
+            _ => {
+                return Err(anyhow!(
+                    "Both the `username` and `password` must be set for \"{}\"",
+                    host
+                ))
+            }
+        }
+
+        Ok(headers)
+    }
+}
+
+fn sensitive_value(value: &str, name: &HeaderName, host: &str) -> anyhow::Result<HeaderValue> {
+    // The value is not included in the error, as it can be a secret
+    let mut value = HeaderValue::from_str(value)
+        .map_err(|_| anyhow!("Invalid value for the \"{}\" header of \"{}\"", name, host))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// Registers the credentials of the `download` section, so they are sent with every request to the matching hosts
+pub(crate) fn register(
+    downloader: &mut Downloader,
+    download: &HashMap<String, HostAuth>,
+) -> anyhow::Result<()> {
+    for (host, auth) in download {
+        let pattern = Pattern::new(host)
+            .with_context(|| format!("Invalid host pattern \"{}\" in `download`", host))?;
+        downloader.register_host_headers(pattern, auth.to_headers(host)?);
+    }
+
+    Ok(())
+}
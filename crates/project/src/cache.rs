@@ -0,0 +1,391 @@
+//! User level cache of downloaded files shared across projects,
+//! files are stored by their SHA-256 checksum in `blobs` and the URLs they were downloaded from in `urls`
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+use std::{env, fs, process};
+
+use anyhow::{anyhow, Context};
+use filetime::FileTime;
+use sha2::{Digest, Sha256};
+
+use crate::checksum::{self, Algorithm};
+
+/// Directory of the cache, `$XDG_CACHE_HOME/crafty` or the platform cache directory,
+/// can be overridden with the `CRAFTY_CACHE_DIR` variable
+pub fn cache_directory() -> anyhow::Result<PathBuf> {
+    if let Some(directory) = env::var_os("CRAFTY_CACHE_DIR") {
+        return Ok(PathBuf::from(directory));
+    }
+
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(dirs::cache_dir)
+        .map(|directory| directory.join("crafty"))
+        .ok_or_else(|| {
+            anyhow!("Could not find the cache directory, set the CRAFTY_CACHE_DIR variable")
+        })
+}
+
+/// Extension of the files being added to the cache
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+fn blob_path(directory: &Path, hash: &str) -> PathBuf {
+    directory.join("blobs").join(hash.to_lowercase())
+}
+
+fn url_path(directory: &Path, url: &str) -> PathBuf {
+    let url_hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    directory.join("urls").join(url_hash)
+}
+
+/// Returns the cached file with the given checksum, or the last file downloaded from the URL if no checksum is known
+pub(crate) fn find(directory: &Path, url: &str, sha256: Option<&str>) -> Option<PathBuf> {
+    let hash = match sha256 {
+        Some(hash) => hash.to_string(),
+        None => {
+            let entry = fs::read_to_string(url_path(directory, url)).ok()?;
+            entry.split_whitespace().next()?.to_string()
+        }
+    };
+
+    let blob = blob_path(directory, &hash);
+    if !blob.is_file() {
+        return None;
+    }
+
+    // The modification time is used to know when a file was last used
+    filetime::set_file_mtime(&blob, FileTime::now()).ok();
+    Some(blob)
+}
+
+/// Hard links (or copies if not possible) a cached file to the destination, returns `false` if the restored file
+/// does not match its checksum (eg. an installed hard link was modified), it is then removed from the cache
+pub(crate) fn restore(blob: &Path, destination: &Path) -> anyhow::Result<bool> {
+    if destination.exists() {
+        fs::remove_file(destination)?;
+    }
+
+    if fs::hard_link(blob, destination).is_err() {
+        fs::copy(blob, destination).with_context(|| {
+            format!(
+                "Could not copy \"{}\" from the cache",
+                destination.display()
+            )
+        })?;
+    }
+
+    let hash = blob.file_name().unwrap_or_default().to_string_lossy();
+    if checksum::file_hash(destination, Algorithm::Sha256)? != hash {
+        fs::remove_file(destination)?;
+        fs::remove_file(blob).ok();
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Adds a downloaded file to the cache
+pub(crate) fn store(directory: &Path, file: &Path, url: &str) -> anyhow::Result<()> {
+    let hash = checksum::file_hash(file, Algorithm::Sha256)?;
+    let blob = blob_path(directory, &hash);
+    let entry = url_path(directory, url);
+    fs::create_dir_all(blob.parent().unwrap())?;
+    fs::create_dir_all(entry.parent().unwrap())?;
+
+    if !blob.exists() {
+        // Files are added with a temporary name so an interrupted copy is never used,
+        // the name is unique as the same file can be stored by concurrent downloads
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let temporary = blob.with_extension(format!(
+            "{}-{}.{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            TEMPORARY_EXTENSION
+        ));
+        if fs::hard_link(file, &temporary).is_err() {
+            fs::copy(file, &temporary)?;
+        }
+        let renamed = fs::rename(&temporary, &blob);
+        fs::remove_file(&temporary).ok();
+        // Another download may have stored the same file in the meantime
+        if renamed.is_err() && !blob.exists() {
+            renamed?;
+        }
+    }
+
+    fs::write(entry, format!("{} {}", hash, url))?;
+    Ok(())
+}
+
+/// A file in the cache
+pub struct CacheEntry {
+    pub hash: String,
+    pub size: u64,
+    pub last_used: SystemTime,
+    /// URLs the file was downloaded from
+    pub urls: Vec<String>,
+}
+
+pub fn list() -> anyhow::Result<Vec<CacheEntry>> {
+    list_in(&cache_directory()?)
+}
+
+fn list_in(directory: &Path) -> anyhow::Result<Vec<CacheEntry>> {
+    let mut entries = vec![];
+
+    let blobs = match fs::read_dir(directory.join("blobs")) {
+        Ok(blobs) => blobs,
+        Err(_) => return Ok(entries),
+    };
+
+    let urls: Vec<(String, String)> = fs::read_dir(directory.join("urls"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|entry| {
+            entry
+                .split_once(' ')
+                .map(|(hash, url)| (hash.to_string(), url.to_string()))
+        })
+        .collect();
+
+    for blob in blobs.flatten() {
+        let metadata = blob.metadata()?;
+        // Files being added by another process are not cached yet
+        let is_temporary = Path::new(&blob.file_name())
+            .extension()
+            .is_some_and(|extension| extension == TEMPORARY_EXTENSION);
+        if !metadata.is_file() || is_temporary {
+            continue;
+        }
+
+        let hash = blob.file_name().to_string_lossy().to_string();
+        entries.push(CacheEntry {
+            urls: urls
+                .iter()
+                .filter(|(url_hash, _)| *url_hash == hash)
+                .map(|(_, url)| url.clone())
+                .collect(),
+            hash,
+            size: metadata.len(),
+            last_used: metadata.modified()?,
+        });
+    }
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+    Ok(entries)
+}
+
+/// Prints the cached files, most recently used first
+pub fn print_list() -> anyhow::Result<()> {
+    let entries = list()?;
+    let now = SystemTime::now();
+
+    for entry in &entries {
+        let age = now
+            .duration_since(entry.last_used)
+            .unwrap_or_default()
+            .as_secs();
+        println!(
+            "{}  {:>10}  {:>8}  {}",
+            &entry.hash[..entry.hash.len().min(12)],
+            format_size(entry.size),
+            format_age(age),
+            entry.urls.first().map(String::as_str).unwrap_or("-")
+        );
+    }
+
+    let total: u64 = entries.iter().map(|entry| entry.size).sum();
+    println!(
+        "{} file(s), {} in \"{}\"",
+        entries.len(),
+        format_size(total),
+        cache_directory()?.display()
+    );
+    Ok(())
+}
+
+/// Removes every file in the cache
+pub fn clean() -> anyhow::Result<()> {
+    let directory = cache_directory()?;
+    if directory.exists() {
+        fs::remove_dir_all(&directory)
+            .with_context(|| format!("Could not remove \"{}\"", directory.display()))?;
+    }
+
+    log::info!("Removed the cache at \"{}\"", directory.display());
+    Ok(())
+}
+
+/// Removes the files that were not used for longer than the given duration
+pub fn prune(older_than: Duration) -> anyhow::Result<()> {
+    let directory = cache_directory()?;
+    let removed = prune_in(&directory, older_than)?;
+
+    let size: u64 = removed.iter().map(|entry| entry.size).sum();
+    log::info!(
+        "Removed {} file(s) from the cache ({})",
+        removed.len(),
+        format_size(size)
+    );
+    Ok(())
+}
+
+/// Removes the unused files from the cache, returning them
+fn prune_in(directory: &Path, older_than: Duration) -> anyhow::Result<Vec<CacheEntry>> {
+    let now = SystemTime::now();
+    let mut removed = vec![];
+
+    for entry in list_in(directory)? {
+        if now.duration_since(entry.last_used).unwrap_or_default() > older_than {
+            fs::remove_file(directory.join("blobs").join(&entry.hash))?;
+            removed.push(entry);
+        }
+    }
+
+    // Remove the URLs of removed files
+    for url in fs::read_dir(directory.join("urls"))
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let is_removed = fs::read_to_string(url.path())
+            .map(|contents| {
+                removed
+                    .iter()
+                    .any(|entry| contents.starts_with(&entry.hash))
+            })
+            .unwrap_or(true);
+        if is_removed {
+            fs::remove_file(url.path())?;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Parses a duration like `30d`, `12h`, `2w` or `90m`
+pub fn parse_duration(input: &str) -> anyhow::Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .with_context(|| format!("Invalid duration \"{}\"", input))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(anyhow!(
+                "Invalid duration unit \"{}\", use s, m, h, d or w",
+                unit
+            ))
+        }
+    };
+
+    Ok(Duration::from_secs(amount * seconds))
+}
+
+fn format_size(size: u64) -> String {
+    match size {
+        size if size >= 1024 * 1024 * 1024 => format!("{:.1} GiB", size as f64 / 1073741824.0),
+        size if size >= 1024 * 1024 => format!("{:.1} MiB", size as f64 / 1048576.0),
+        size if size >= 1024 => format!("{:.1} KiB", size as f64 / 1024.0),
+        size => format!("{} B", size),
+    }
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        seconds if seconds >= 24 * 60 * 60 => format!("{}d ago", seconds / (24 * 60 * 60)),
+        seconds if seconds >= 60 * 60 => format!("{}h ago", seconds / (60 * 60)),
+        seconds => format!("{}m ago", seconds / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90m").unwrap(), Duration::from_secs(90 * 60));
+        assert_eq!(
+            parse_duration(" 12h ").unwrap(),
+            Duration::from_secs(12 * 3600)
+        );
+        assert_eq!(
+            parse_duration("2w").unwrap(),
+            Duration::from_secs(14 * 86400)
+        );
+        assert_eq!(
+            parse_duration("30").unwrap(),
+            parse_duration("30d").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("30y").is_err());
+        assert!(parse_duration("-1d").is_err());
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn stores_the_same_file_concurrently() {
+        let directory = tempfile::tempdir().unwrap();
+        let cache = directory.path().join("cache");
+        let file = directory.path().join("plugin.jar");
+        fs::write(&file, "plugin").unwrap();
+
+        std::thread::scope(|scope| {
+            for index in 0..8 {
+                let (cache, file) = (&cache, &file);
+                scope.spawn(move || {
+                    store(cache, file, &format!("https://example.com/{}.jar", index)).unwrap()
+                });
+            }
+        });
+
+        let hash = checksum::file_hash(&file, Algorithm::Sha256).unwrap();
+        let blob = find(&cache, "https://example.com/3.jar", None).unwrap();
+        assert_eq!(blob, blob_path(&cache, &hash));
+        assert_eq!(fs::read_dir(cache.join("blobs")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn skips_temporary_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("plugin.jar");
+        fs::write(&file, "plugin").unwrap();
+        store(directory.path(), &file, "https://example.com/plugin.jar").unwrap();
+        fs::write(directory.path().join("blobs/abc.1-0.tmp"), "partial").unwrap();
+
+        let entries = list_in(directory.path()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].urls, vec!["https://example.com/plugin.jar"]);
+
+        let blob = &entries[0].hash;
+        let last_used = FileTime::from_unix_time(0, 0);
+        filetime::set_file_mtime(blob_path(directory.path(), blob), last_used).unwrap();
+        let removed = prune_in(directory.path(), Duration::from_secs(60)).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(list_in(directory.path()).unwrap().is_empty());
+    }
+}
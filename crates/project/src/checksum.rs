@@ -0,0 +1,179 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use anyhow::anyhow;
+use common::utils;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha512 => "SHA-512",
+        }
+    }
+}
+
+/// Computes the hex encoded checksum of a file,
+/// or of the relative paths and contents of the files in a directory
+pub(crate) fn file_hash(path: &Path, algorithm: Algorithm) -> anyhow::Result<String> {
+    fn digest<D: Digest + io::Write>(path: &Path) -> anyhow::Result<String> {
+        let mut hasher = D::new();
+        if path.is_dir() {
+            for entry in WalkDir::new(path).sort_by_file_name() {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+
+                let relative_path = entry.path().strip_prefix(path)?.to_string_lossy();
+                Digest::update(&mut hasher, relative_path.replace('\\', "/").as_bytes());
+                Digest::update(&mut hasher, [0]);
+                io::copy(&mut File::open(entry.path())?, &mut hasher)?;
+            }
+        } else {
+            io::copy(&mut File::open(path)?, &mut hasher)?;
+        }
+
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect())
+    }
+
+    match algorithm {
+        Algorithm::Sha1 => digest::<Sha1>(path),
+        Algorithm::Sha256 => digest::<Sha256>(path),
+        Algorithm::Sha512 => digest::<Sha512>(path),
+    }
+}
+
+/// Checks the checksum of a downloaded file, the file is removed if it does not match
+pub(crate) fn verify(path: &Path, algorithm: Algorithm, expected: &str) -> anyhow::Result<()> {
+    let actual = file_hash(path, algorithm)?;
+
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        if path.is_dir() {
+            fs::remove_dir_all(path).ok();
+        } else {
+            fs::remove_file(path).ok();
+        }
+        return Err(anyhow!(
+            "Checksum mismatch for \"{}\" (expected {} {}, got {})",
+            path.display(),
+            algorithm.name(),
+            expected.trim(),
+            actual
+        ));
+    }
+
+    Ok(())
+}
+
+/// Verifies the `sha256` and `sha512` checksums declared for a file, if any
+pub(crate) fn verify_declared(
+    path: &Path,
+    sha256: Option<&str>,
+    sha512: Option<&str>,
+) -> anyhow::Result<()> {
+    if let Some(sha256) = sha256 {
+        verify(path, Algorithm::Sha256, sha256)?;
+    }
+    if let Some(sha512) = sha512 {
+        verify(path, Algorithm::Sha512, sha512)?;
+    }
+
+    Ok(())
+}
+
+/// Whether a previously installed file still has its recorded SHA-256 checksum,
+/// local sources are expected to change, so they are compared with the installed file too
+pub(crate) fn is_unchanged(installed: &Path, source: &str, hash: &str) -> anyhow::Result<bool> {
+    if !installed.exists() || file_hash(installed, Algorithm::Sha256)? != hash {
+        return Ok(false);
+    }
+
+    if !utils::is_url(source) {
+        let source = Path::new(source);
+        if !source.exists() || file_hash(source, Algorithm::Sha256)? != hash {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn hashes_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("file.txt");
+        fs::write(&file, "hello").unwrap();
+
+        assert_eq!(file_hash(&file, Algorithm::Sha256).unwrap(), HELLO_SHA256);
+        assert_eq!(
+            file_hash(&file, Algorithm::Sha1).unwrap(),
+            "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"
+        );
+    }
+
+    #[test]
+    fn hashes_directories_with_their_paths() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fs::write(first.path().join("a.txt"), "hello").unwrap();
+        fs::write(second.path().join("b.txt"), "hello").unwrap();
+
+        assert_ne!(
+            file_hash(first.path(), Algorithm::Sha256).unwrap(),
+            file_hash(second.path(), Algorithm::Sha256).unwrap()
+        );
+    }
+
+    #[test]
+    fn removes_files_that_do_not_match() {
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("file.txt");
+        fs::write(&file, "hello").unwrap();
+
+        verify(&file, Algorithm::Sha256, &HELLO_SHA256.to_uppercase()).unwrap();
+        assert!(file.exists());
+
+        assert!(verify_declared(&file, None, Some("00")).is_err());
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn compares_local_sources_with_the_installed_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let installed = directory.path().join("installed.txt");
+        let source = directory.path().join("source.txt");
+        fs::write(&installed, "hello").unwrap();
+        fs::write(&source, "hello").unwrap();
+        let source = source.to_string_lossy();
+
+        assert!(is_unchanged(&installed, &source, HELLO_SHA256).unwrap());
+        assert!(is_unchanged(&installed, "https://example.com/a.jar", HELLO_SHA256).unwrap());
+
+        fs::write(source.as_ref(), "changed").unwrap();
+        assert!(!is_unchanged(&installed, &source, HELLO_SHA256).unwrap());
+        assert!(!is_unchanged(&directory.path().join("missing"), &source, HELLO_SHA256).unwrap());
+    }
+}
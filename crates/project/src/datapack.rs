@@ -0,0 +1,292 @@
+//! Data packs, installed in the `datapacks` folder of the world
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use zip::ZipArchive;
+
+const METADATA_FILE: &str = "pack.mcmeta";
+const DEFAULT_LEVEL_NAME: &str = "world";
+
+/// Data pack format of each Minecraft version, by the first version using it
+const PACK_FORMATS: &[((u32, u32, u32), u32)] = &[
+    ((1, 13, 0), 4),
+    ((1, 15, 0), 5),
+    ((1, 16, 2), 6),
+    ((1, 17, 0), 7),
+    ((1, 18, 0), 8),
+    ((1, 18, 2), 9),
+    ((1, 19, 0), 10),
+    ((1, 19, 4), 12),
+    ((1, 20, 0), 15),
+    ((1, 20, 2), 18),
+    ((1, 20, 3), 26),
+    ((1, 20, 5), 41),
+    ((1, 21, 0), 48),
+    ((1, 21, 2), 57),
+    ((1, 21, 4), 61),
+    ((1, 21, 5), 71),
+    ((1, 21, 6), 80),
+    ((1, 21, 7), 81),
+];
+/// Last version the formats above are known for
+const LAST_KNOWN_VERSION: (u32, u32, u32) = (1, 21, 8);
+
+#[derive(Deserialize, Debug)]
+struct PackMetadata {
+    pack: Pack,
+}
+
+#[derive(Deserialize, Debug)]
+struct Pack {
+    pack_format: Option<u32>,
+    supported_formats: Option<SupportedFormats>,
+    min_format: Option<FormatVersion>,
+    max_format: Option<FormatVersion>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum SupportedFormats {
+    Single(u32),
+    Range([u32; 2]),
+    Bounds {
+        min_inclusive: u32,
+        max_inclusive: u32,
+    },
+}
+
+/// Format used by `min_format` and `max_format`, either `<major>` or `[<major>, <minor>]`
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum FormatVersion {
+    Major(u32),
+    Full(Vec<u32>),
+}
+
+impl FormatVersion {
+    fn major(&self) -> Option<u32> {
+        match self {
+            FormatVersion::Major(major) => Some(*major),
+            FormatVersion::Full(parts) => parts.first().copied(),
+        }
+    }
+}
+
+impl Pack {
+    /// Range of data pack formats the pack declares to support
+    fn formats(&self) -> Option<(u32, u32)> {
+        if let (Some(min), Some(max)) = (&self.min_format, &self.max_format) {
+            return min.major().zip(max.major());
+        }
+
+        match &self.supported_formats {
+            Some(SupportedFormats::Single(format)) => Some((*format, *format)),
+            Some(SupportedFormats::Range([min, max])) => Some((*min, *max)),
+            Some(SupportedFormats::Bounds {
+                min_inclusive,
+                max_inclusive,
+            }) => Some((*min_inclusive, *max_inclusive)),
+            None => self.pack_format.map(|format| (format, format)),
+        }
+    }
+
+    /// Whether the pack declares a range of formats, instead of only the `pack_format` it was made for
+    fn has_supported_formats(&self) -> bool {
+        self.supported_formats.is_some() || (self.min_format.is_some() && self.max_format.is_some())
+    }
+}
+
+/// Checks that a data pack (a ZIP file or a directory) has a `pack.mcmeta` file at its root
+/// and that its format is supported by the server version, if it is known.
+/// Minecraft still loads packs made for another format, so it only fails if their supported formats exclude it
+pub(crate) fn validate(path: &Path, server_version: Option<&str>) -> anyhow::Result<()> {
+    let contents = read_metadata(path)?.ok_or_else(|| {
+        anyhow!(
+            "\"{}\" is not a data pack, \"{}\" was not found at its root",
+            path.display(),
+            METADATA_FILE
+        )
+    })?;
+    let metadata: PackMetadata = serde_json::from_str(&contents)
+        .with_context(|| format!("Could not parse the \"{}\" file", METADATA_FILE))?;
+    let (min, max) = metadata.pack.formats().ok_or_else(|| {
+        anyhow!(
+            "The \"{}\" file does not declare a pack_format",
+            METADATA_FILE
+        )
+    })?;
+
+    let Some(server_version) = server_version else {
+        return Ok(());
+    };
+    let Some(format) = pack_format(server_version) else {
+        log::warn!(
+            "Could not check the data pack format of \"{}\" for Minecraft {}",
+            path.display(),
+            server_version
+        );
+        return Ok(());
+    };
+
+    if format < min || format > max {
+        let supported = if min == max {
+            min.to_string()
+        } else {
+            format!("{}-{}", min, max)
+        };
+        if metadata.pack.has_supported_formats() {
+            return Err(anyhow!(
+                "The data pack format {} is not compatible with Minecraft {} (format {})",
+                supported,
+                server_version,
+                format
+            ));
+        }
+
+        log::warn!(
+            "The data pack \"{}\" was made for format {}, it may not work with Minecraft {} (format {})",
+            path.display(),
+            supported,
+            server_version,
+            format
+        );
+    }
+
+    Ok(())
+}
+
+fn read_metadata(path: &Path) -> anyhow::Result<Option<String>> {
+    if path.is_dir() {
+        let metadata_path = path.join(METADATA_FILE);
+        return Ok(metadata_path
+            .is_file()
+            .then(|| fs::read_to_string(metadata_path))
+            .transpose()?);
+    }
+
+    let mut archive = ZipArchive::new(File::open(path)?)
+        .with_context(|| format!("\"{}\" is not a ZIP file", path.display()))?;
+    let mut contents = String::new();
+    match archive.by_name(METADATA_FILE) {
+        Ok(mut file) => file.read_to_string(&mut contents)?,
+        Err(_) => return Ok(None),
+    };
+
+    Ok(Some(contents))
+}
+
+/// Data pack format of a Minecraft release (eg. `1.20.4`), `None` for unknown versions and snapshots
+fn pack_format(version: &str) -> Option<u32> {
+    let mut parts = version.split('.').map(|part| part.parse::<u32>().ok());
+    let version = (
+        parts.next()??,
+        parts.next()??,
+        parts.next().unwrap_or(Some(0))?,
+    );
+    if parts.next().is_some() || version > LAST_KNOWN_VERSION {
+        return None;
+    }
+
+    PACK_FORMATS
+        .iter()
+        .rev()
+        .find(|(first_version, _)| *first_version <= version)
+        .map(|(_, format)| *format)
+}
+
+/// Directory of the world data packs are installed in,
+/// taken from the `level-name` property of the `server.properties` file
+pub(crate) fn datapacks_directory(server_directory: &Path) -> PathBuf {
+    let level_name = fs::read_to_string(server_directory.join("server.properties"))
+        .ok()
+        .and_then(|properties| {
+            properties
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.starts_with('#') && !line.starts_with('!'))
+                .filter_map(|line| line.split_once('='))
+                .find(|(key, _)| key.trim() == "level-name")
+                .map(|(_, value)| value.trim().to_string())
+        })
+        .filter(|level_name| !level_name.is_empty())
+        .unwrap_or_else(|| DEFAULT_LEVEL_NAME.into());
+
+    server_directory.join(level_name).join("datapacks")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(metadata: &str) -> tempfile::TempDir {
+        let directory = tempfile::tempdir().unwrap();
+        fs::write(directory.path().join(METADATA_FILE), metadata).unwrap();
+        directory
+    }
+
+    #[test]
+    fn finds_the_pack_format_of_releases() {
+        assert_eq!(pack_format("1.20.4"), Some(26));
+        assert_eq!(pack_format("1.20"), Some(15));
+        assert_eq!(pack_format("1.19.3"), Some(10));
+        assert_eq!(pack_format("1.21.8"), Some(81));
+    }
+
+    #[test]
+    fn ignores_unknown_versions_and_snapshots() {
+        assert_eq!(pack_format("1.12.2"), None);
+        assert_eq!(pack_format("1.21.9"), None);
+        assert_eq!(pack_format("24w03a"), None);
+        assert_eq!(pack_format("1.20.4-pre1"), None);
+        assert_eq!(pack_format("1.20.4.1"), None);
+    }
+
+    #[test]
+    fn checks_the_supported_formats() {
+        let single = pack(r#"{"pack": {"pack_format": 26}}"#);
+        assert!(validate(single.path(), Some("1.20.4")).is_ok());
+        assert!(validate(single.path(), None).is_ok());
+        // Only packs declaring their supported formats are rejected
+        assert!(validate(single.path(), Some("1.20.6")).is_ok());
+
+        let range = pack(r#"{"pack": {"pack_format": 15, "supported_formats": [15, 41]}}"#);
+        assert!(validate(range.path(), Some("1.20.5")).is_ok());
+        assert!(validate(range.path(), Some("1.21")).is_err());
+
+        let bounds = pack(r#"{"pack": {"min_format": [80, 0], "max_format": 81}}"#);
+        assert!(validate(bounds.path(), Some("1.21.7")).is_ok());
+        assert!(validate(bounds.path(), Some("1.21.5")).is_err());
+    }
+
+    #[test]
+    fn requires_a_pack_metadata_file() {
+        let directory = tempfile::tempdir().unwrap();
+        assert!(validate(directory.path(), None).is_err());
+
+        let invalid = pack(r#"{"pack": {}}"#);
+        assert!(validate(invalid.path(), None).is_err());
+    }
+
+    #[test]
+    fn installs_in_the_world_of_the_server() {
+        let directory = tempfile::tempdir().unwrap();
+        assert_eq!(
+            datapacks_directory(directory.path()),
+            directory.path().join("world/datapacks")
+        );
+
+        fs::write(
+            directory.path().join("server.properties"),
+            "#level-name=commented\nlevel-name = survival\n",
+        )
+        .unwrap();
+        assert_eq!(
+            datapacks_directory(directory.path()),
+            directory.path().join("survival/datapacks")
+        );
+    }
+}
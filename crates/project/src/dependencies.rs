@@ -1,11 +1,20 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
-use crate::installer;
-use crate::manifests::{DependenciesManifest, DependencyDetails, Manifest};
-use anyhow::anyhow;
+use crate::checksum::{self, Algorithm};
+use crate::descriptor::Descriptor;
+use crate::installer::{self, Downloader};
+use crate::lockfile::{LockedDependency, Lockfile};
+use crate::manifests::{
+    DependenciesManifest, DependencyDetails, Manifest, ServerState, VersionManifest,
+};
+use crate::sources::{git, github, hangar, jenkins, maven, modrinth};
+use crate::{archive, datapack, descriptor};
+use crate::{InstallOptions, ProjectMetadata, Server};
+use anyhow::{anyhow, Context};
 use common::utils;
+use futures::{stream, StreamExt};
 use serde::{Deserialize, Serialize};
 
 // Workaround for https://github.com/serde-rs/serde/issues/368
@@ -21,79 +30,740 @@ pub struct Dependency {
     pub dependency_type: DependencyType,
     #[serde(default = "default_bool::<false>")]
     pub required: bool,
+    /// Expected checksums of the dependency, the install fails if they do not match
+    pub sha256: Option<String>,
+    pub sha512: Option<String>,
+    /// Pattern of the release asset to install, for `github:` sources
+    pub asset: Option<String>,
+    /// Pattern of the build artifact to install, for `jenkins:` and `git+` sources
+    pub artifact: Option<String>,
+    /// Command building the artifact in the repository of `git+` sources (eg. `./gradlew shadowJar`)
+    pub build: Option<String>,
+    /// Directory to install the dependency in, relative to the server directory, for `custom` dependencies
+    pub target: Option<String>,
+    /// Whether the dependency is a ZIP archive whose files should be installed
+    #[serde(default)]
+    pub extract: bool,
+    /// Patterns of the files to extract from the archive (eg. `**/*.jar`), all files by default
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Number of leading directories to remove from the paths of the extracted files
+    #[serde(rename = "strip-components", default)]
+    pub strip_components: usize,
+    /// Profiles the dependency is used in (eg. `[dev]`), it is used in every profile if empty
+    #[serde(default)]
+    pub profiles: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+/// A Maven repository used to resolve `maven:` dependencies
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Repository {
+    pub url: String,
+    /// Credentials for the repository, environment variables can be used with `${VARIABLE}`
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum DependencyType {
     Mod,
     #[default]
     Plugin,
     DataPack,
+    /// Added to the classpath of the server
+    Library,
+    ResourcePack,
+    /// Installed in the `target` directory
+    Custom,
 }
 
+/// Directory library dependencies are installed in, relative to the server directory
+pub(crate) const LIBRARIES_DIRECTORY: &str = "lib";
+
+/// Brands that load the game in their own class loader (`paper`, its forks and `vanilla`) or are launched with an
+/// argument file (`forge`), library dependencies would never be seen by their plugins and mods
+const BRANDS_WITHOUT_LIBRARIES: [&str; 6] =
+    ["paper", "folia", "purpur", "vanilla", "forge", "neoforge"];
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DependencyFile {
     pub filename: String,
     pub source: String,
+    /// SHA-256 checksum of the installed file
     pub hash: String,
 }
 
-pub async fn install_dependencies(
-    dependencies: &HashMap<String, Dependency>,
+/// A dependency resolved from its declaration in `crafty.yml`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ResolvedDependency {
+    /// URL or local path of the file to install
+    pub url: String,
+    /// File name to save the download as, defaults to the last segment of the URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+}
+
+/// Fails if the project has library dependencies but its server cannot add them to its classpath
+fn check_libraries(project: &ProjectMetadata) -> anyhow::Result<()> {
+    let brand = project
+        .server
+        .brand
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    if !BRANDS_WITHOUT_LIBRARIES.contains(&brand.as_str()) {
+        return Ok(());
+    }
+
+    match project
+        .dependencies
+        .iter()
+        .find(|(_, dependency)| dependency.dependency_type == DependencyType::Library)
+    {
+        Some((id, _)) => Err(anyhow!(
+            "\"{}\" is a `library` dependency, which is not supported by \"{}\" servers, install it as a `plugin` or `mod` instead",
+            id,
+            brand
+        )),
+        None => Ok(()),
+    }
+}
+
+pub(crate) async fn install_dependencies(
+    project: &ProjectMetadata,
     root_directory: &Path,
+    lockfile: &mut Lockfile,
+    options: &InstallOptions,
+    downloader: &Downloader,
 ) -> anyhow::Result<()> {
+    check_libraries(project)?;
+
     let mut installed_dependencies: HashMap<String, DependencyDetails> = HashMap::new();
+    let dependencies = &project.dependencies;
+    let previous = DependenciesManifest::load_manifest(root_directory)
+        .map(|manifest| manifest.0)
+        .unwrap_or_default();
+
+    let installs: Vec<_> = dependencies
+        .iter()
+        .map(|(id, dependency)| {
+            let locked = lockfile
+                .locked_dependency(id, dependency)
+                .filter(|_| !options.should_update(id));
+            let previous = previous.get(id).filter(|_| !options.force);
+
+            async move {
+                let installed = fetch_dependency(
+                    id,
+                    dependency,
+                    project,
+                    root_directory,
+                    locked,
+                    previous,
+                    downloader,
+                )
+                .await;
+                (id, dependency, installed)
+            }
+        })
+        .collect();
+
+    // Dependencies are downloaded concurrently, the first error cancels the remaining downloads
+    let mut installs = stream::iter(installs).buffer_unordered(options.jobs.max(1));
+    while let Some((id, dependency, installed)) = installs.next().await {
+        if let Some((details, resolved)) = installed? {
+            if let Some(resolved) = resolved {
+                lockfile
+                    .dependencies
+                    .insert(id.clone(), LockedDependency::new(dependency, resolved));
+            }
+            installed_dependencies.insert(id.clone(), details);
+        }
+    }
+
+    lockfile
+        .dependencies
+        .retain(|id, _| dependencies.contains_key(id));
+
+    // The manifest is saved even if the validation fails, so it always lists the installed files,
+    // which are only removed once the dependencies are valid
+    let validation = descriptor::validate(
+        &installed_dependencies,
+        installed_server_version(root_directory, project).as_deref(),
+    );
+    let manifest = DependenciesManifest::new(installed_dependencies);
+    manifest.save_manifest(&root_directory.join(".crafty").join("dependencies.yml"))?;
+    validation?;
+
+    remove_stale_files(
+        &root_directory.join(".crafty").join("dependencies"),
+        &manifest.0,
+    )
+}
+
+/// Installs a single dependency into `.crafty/dependencies` and updates its lockfile entry,
+/// returns `None` if the dependency is not required and could not be installed
+pub(crate) async fn install_dependency(
+    id: &str,
+    dependency: &Dependency,
+    project: &ProjectMetadata,
+    root_directory: &Path,
+    lockfile: &mut Lockfile,
+    options: &InstallOptions,
+    downloader: &Downloader,
+) -> anyhow::Result<Option<DependencyDetails>> {
+    check_libraries(project)?;
+
+    let locked = lockfile
+        .locked_dependency(id, dependency)
+        .filter(|_| !options.should_update(id));
+
+    let installed = fetch_dependency(
+        id,
+        dependency,
+        project,
+        root_directory,
+        locked,
+        None,
+        downloader,
+    )
+    .await?;
+    Ok(installed.map(|(details, resolved)| {
+        if let Some(resolved) = resolved {
+            lockfile
+                .dependencies
+                .insert(id.to_string(), LockedDependency::new(dependency, resolved));
+        }
+        details
+    }))
+}
+
+/// Resolves (unless locked) and downloads a dependency, returning what it was resolved to,
+/// the download is skipped if the previously installed file matches
+async fn fetch_dependency(
+    id: &str,
+    dependency: &Dependency,
+    project: &ProjectMetadata,
+    root_directory: &Path,
+    locked: Option<ResolvedDependency>,
+    previous: Option<&DependencyDetails>,
+    downloader: &Downloader,
+) -> anyhow::Result<Option<(DependencyDetails, Option<ResolvedDependency>)>> {
+    let dependencies_directory = root_directory.join(".crafty").join("dependencies");
+    let target = install_target(id, dependency)?;
+    let is_locked = locked.is_some();
+    let git_source = dependency
+        .source
+        .as_deref()
+        .filter(|source| git::is_git_source(source));
+    let resolved = match (locked, git_source) {
+        // Git dependencies are built again, from the locked commit if any
+        (locked, Some(source)) => git::resolve(
+            downloader,
+            source,
+            locked.and_then(|locked| locked.version).as_deref(),
+            previous.and_then(|previous| previous.version.as_deref()),
+            dependency.build.as_deref(),
+            dependency.artifact.as_deref(),
+            &root_directory.join(".crafty").join("src").join(id),
+        )
+        .await
+        .map(Some),
+        (Some(locked), None) => Ok(Some(locked)),
+        (None, None) => resolve_dependency(id, dependency, project, downloader).await,
+    };
+    let resolved = match resolved {
+        Ok(resolved) => resolved,
+        Err(err) if !dependency.required => {
+            log::warn!("Could not resolve {} ({}), skipping...", id, err);
+            return Ok(None);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let mut files = vec![];
+    let mut archive = None;
+    let mut version = None;
+    let mut locked = None;
+
+    if let Some(mut resolved) = resolved {
+        let installed_file = match previous {
+            Some(previous) => {
+                installed_file(previous, &resolved, root_directory, &dependencies_directory)?
+            }
+            None => None,
+        };
+        let install = match installed_file {
+            Some(file) => {
+                log::info!("\"{}\" ({}) is up to date", id, file.filename);
+                Ok(file)
+            }
+            None => {
+                install_from_source(
+                    id,
+                    &resolved,
+                    &dependency.dependency_type,
+                    root_directory,
+                    dependencies_directory.clone(),
+                    downloader,
+                )
+                .await
+            }
+        };
+
+        let file = match install {
+            Ok(file) => file,
+            Err(err) => {
+                if dependency.required {
+                    return Err(err);
+                } else {
+                    log::warn!("Could not install {} ({}), skipping...", id, err);
+                    return Ok(None);
+                }
+            }
+        };
+
+        // A checksum mismatch fails the install even if the dependency is not required
+        let file_path = dependencies_directory.join(&file.filename);
+        verify_dependency(&file_path, dependency, &resolved).with_context(|| {
+            if is_locked {
+                format!(
+                    "Could not verify dependency \"{}\", if it was changed on purpose, run `crafty install --update {}`",
+                    id, id
+                )
+            } else {
+                format!("Could not verify dependency \"{}\"", id)
+            }
+        })?;
+
+        if dependency.dependency_type == DependencyType::DataPack && !dependency.extract {
+            let server_version = installed_server_version(root_directory, project);
+            datapack::validate(&file_path, server_version.as_deref())
+                .with_context(|| format!("Could not install data pack \"{}\"", id))?;
+        }
+
+        version = resolved.version.clone();
+        resolved.sha256 = Some(file.hash.clone());
+        // The built file is local to the machine, git dependencies are locked to their commit
+        if let Some(source) = git_source {
+            resolved.url = source.to_string();
+        }
+        locked = Some(resolved);
+
+        if dependency.extract {
+            files = extract_dependency(id, &file, dependency, &dependencies_directory)
+                .with_context(|| format!("Could not extract dependency \"{}\"", id))?;
+            archive = Some(file);
+        } else {
+            files = vec![file];
+        }
+    }
+
+    let descriptor = match dependency.dependency_type {
+        DependencyType::Plugin | DependencyType::Mod => {
+            read_descriptor(id, &files, &dependencies_directory, &project.server)
+        }
+        _ => None,
+    };
+
+    let details = DependencyDetails {
+        dependency_type: dependency.dependency_type.clone(),
+        target,
+        version,
+        archive,
+        files,
+        descriptor,
+        profiles: dependency.profiles.clone(),
+    };
+    Ok(Some((details, locked)))
+}
+
+/// Version of the installed server, the declared one is used if the server was not installed
+/// (the declared version can be `latest-release` for example)
+pub(crate) fn installed_server_version(
+    root_directory: &Path,
+    project: &ProjectMetadata,
+) -> Option<String> {
+    VersionManifest::load_manifest(root_directory)
+        .ok()
+        .and_then(|manifest| manifest.version)
+        .or_else(|| project.server.version.clone())
+}
 
-    for (id, dependency) in dependencies {
-        let mut files = vec![];
+/// Reads the descriptor of the first JAR file of a dependency that has one
+fn read_descriptor(
+    id: &str,
+    files: &[DependencyFile],
+    dependencies_directory: &Path,
+    server: &Server,
+) -> Option<Descriptor> {
+    files
+        .iter()
+        .filter(|file| file.filename.ends_with(".jar"))
+        .find_map(|file| {
+            let path = dependencies_directory.join(&file.filename);
+            descriptor::read(&path, server).unwrap_or_else(|err| {
+                log::warn!("Could not read the descriptor of \"{}\" ({:#})", id, err);
+                None
+            })
+        })
+}
 
-        if let Some(source) = &dependency.source {
-            let file = match install_from_source(
-                id,
-                source,
-                root_directory.join(".crafty").join("dependencies"),
+/// Extracts an archive dependency into `.crafty/dependencies/<id>`, the archive is removed once extracted
+fn extract_dependency(
+    id: &str,
+    archive_file: &DependencyFile,
+    dependency: &Dependency,
+    dependencies_directory: &Path,
+) -> anyhow::Result<Vec<DependencyFile>> {
+    let archive_path = dependencies_directory.join(&archive_file.filename);
+    let destination = dependencies_directory.join(id);
+    if destination.exists() {
+        fs::remove_dir_all(&destination)?;
+    }
+
+    log::info!("Extracting \"{}\" ({})...", id, archive_file.filename);
+    let extracted = archive::extract(
+        &archive_path,
+        &destination,
+        &dependency.include,
+        dependency.strip_components,
+    )?;
+    fs::remove_file(&archive_path)?;
+
+    extracted
+        .into_iter()
+        .map(|path| {
+            Ok(DependencyFile {
+                filename: format!("{}/{}", id, path.to_string_lossy().replace('\\', "/")),
+                source: archive_file.source.clone(),
+                hash: checksum::file_hash(&destination.join(&path), Algorithm::Sha256)?,
+            })
+        })
+        .collect()
+}
+
+/// Checks the `target` directory of a dependency, which is only used (and required) by `custom` dependencies
+fn install_target(id: &str, dependency: &Dependency) -> anyhow::Result<Option<String>> {
+    let target = match (&dependency.dependency_type, &dependency.target) {
+        (DependencyType::Custom, Some(target)) => target,
+        (DependencyType::Custom, None) => {
+            return Err(anyhow!(
+                "The custom dependency \"{}\" needs a `target` directory",
+                id
+            ))
+        }
+        (_, Some(_)) => {
+            return Err(anyhow!(
+                "The `target` of \"{}\" can only be used with the `custom` type",
+                id
+            ))
+        }
+        (_, None) => return Ok(None),
+    };
+
+    let is_inside = Path::new(target)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !is_inside {
+        return Err(anyhow!(
+            "The `target` of \"{}\" must be a relative path inside the server directory",
+            id
+        ));
+    }
+
+    Ok(Some(target.clone()))
+}
+
+/// Resolves where a dependency should be installed from,
+/// dependencies with a version but no source are resolved from Modrinth using their id
+async fn resolve_dependency(
+    id: &str,
+    dependency: &Dependency,
+    project: &ProjectMetadata,
+    downloader: &Downloader,
+) -> anyhow::Result<Option<ResolvedDependency>> {
+    let server = &project.server;
+    let version = dependency.version.as_deref().unwrap_or("latest");
+
+    match dependency.source.as_deref() {
+        Some(source) => match source.split_once(':') {
+            Some(("github", repository)) => {
+                github::resolve(downloader, repository, version, dependency.asset.as_deref())
+                    .await
+                    .map(Some)
+            }
+            Some(("hangar", project)) => hangar::resolve(downloader, project, version, server)
+                .await
+                .map(Some),
+            Some(("jenkins", job_url)) => {
+                jenkins::resolve(downloader, job_url, version, dependency.artifact.as_deref())
+                    .await
+                    .map(Some)
+            }
+            Some(("maven", coordinates)) => {
+                maven::resolve(downloader, coordinates, version, &project.repositories)
+                    .await
+                    .map(Some)
+            }
+            Some(("modrinth", slug)) => modrinth::resolve(
+                downloader,
+                slug,
+                version,
+                &dependency.dependency_type,
+                server,
             )
             .await
-            {
-                Ok(file) => file,
-                Err(err) => {
-                    if dependency.required {
-                        return Err(err);
-                    } else {
-                        log::warn!("Could not install {} ({}), skipping...", id, err);
-                        continue;
-                    }
-                }
+            .map(Some),
+            _ => Ok(Some(ResolvedDependency {
+                url: source.to_string(),
+                version: dependency.version.clone(),
+                ..Default::default()
+            })),
+        },
+        None if dependency.version.is_some() => {
+            modrinth::resolve(downloader, id, version, &dependency.dependency_type, server)
+                .await
+                .map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Resolves the latest version of a dependency and the latest one compatible with the server,
+/// sources without Minecraft version information report the same version for both
+pub(crate) async fn latest_versions(
+    id: &str,
+    dependency: &Dependency,
+    project: &ProjectMetadata,
+    downloader: &Downloader,
+) -> anyhow::Result<(Option<String>, Option<String>)> {
+    let server = &project.server;
+    let latest = Dependency {
+        version: Some("latest".into()),
+        ..dependency.clone()
+    };
+
+    // Dependencies with a version but no source are resolved from Modrinth
+    let source = match dependency.source.as_deref() {
+        Some(source) => source.split_once(':'),
+        None if dependency.version.is_some() => Some(("modrinth", id)),
+        None => None,
+    };
+
+    match source {
+        Some(("modrinth", _)) => {
+            let compatible = resolve_dependency(id, &latest, project, downloader).await?;
+            let any_version = ProjectMetadata {
+                server: Server {
+                    version: None,
+                    ..server.clone()
+                },
+                ..project.clone()
             };
-            files = vec![file];
-        } else if let Some(_version) = &dependency.version {
-            // TODO: Implement
-            // download_version(id, version)?;
-            files = vec![];
+            let newest = resolve_dependency(id, &latest, &any_version, downloader).await?;
+
+            Ok((
+                newest.and_then(|resolved| resolved.version),
+                compatible.and_then(|resolved| resolved.version),
+            ))
         }
+        Some(("hangar", hangar_project)) => {
+            let newest = resolve_dependency(id, &latest, project, downloader).await?;
+            let compatible = hangar::latest_compatible(downloader, hangar_project, server).await?;
 
-        installed_dependencies.insert(
-            id.clone(),
-            DependencyDetails {
-                dependency_type: DependencyType::Plugin,
-                files,
-            },
-        );
+            Ok((newest.and_then(|resolved| resolved.version), compatible))
+        }
+        Some(("github" | "jenkins" | "maven", _)) => {
+            let newest = resolve_dependency(id, &latest, project, downloader)
+                .await?
+                .and_then(|resolved| resolved.version);
+
+            Ok((newest.clone(), newest))
+        }
+        _ => Ok((None, None)),
+    }
+}
+
+/// Detects the id and source of a dependency added with `crafty add`,
+/// project pages of known platforms are turned into their source prefix
+/// and anything that is not a URL, a path or a prefixed source is looked up on Modrinth
+pub(crate) fn detect_source(target: &str, root_directory: &Path) -> (String, Option<String>) {
+    if let Some((prefix, value)) = target.split_once(':') {
+        let id = match prefix {
+            "github" | "hangar" | "modrinth" => value.rsplit('/').next(),
+            "jenkins" => value.trim_end_matches('/').rsplit('/').next(),
+            "maven" => value.split(':').nth(1),
+            _ => None,
+        };
+        if let Some(id) = id {
+            return (id.to_lowercase(), Some(target.into()));
+        }
+    }
+
+    if let Ok(url) = url::Url::parse(target) {
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+            .unwrap_or_default();
+
+        let detected = match (url.host_str(), segments.as_slice()) {
+            (Some("modrinth.com"), [_, slug, ..]) => {
+                Some((slug.to_string(), format!("modrinth:{}", slug)))
+            }
+            (Some("hangar.papermc.io"), [owner, slug, ..]) => {
+                Some((slug.to_string(), format!("hangar:{}/{}", owner, slug)))
+            }
+            (Some("github.com"), [owner, repo] | [owner, repo, "releases"]) => {
+                Some((repo.to_string(), format!("github:{}/{}", owner, repo)))
+            }
+            _ if !segments.contains(&"artifact")
+                && segments.iter().rev().nth(1) == Some(&"job") =>
+            {
+                let job = segments.last().unwrap();
+                let job_url = target.trim_end_matches('/');
+                Some((job.to_string(), format!("jenkins:{}", job_url)))
+            }
+            _ => None,
+        };
+        if let Some((id, source)) = detected {
+            return (id.to_lowercase(), Some(source));
+        }
+
+        let file_name = utils::get_filename_from_url(target);
+        return (file_stem(&file_name), Some(target.into()));
+    }
+
+    if root_directory.join(target).is_file() {
+        let file_name = Path::new(target)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(target);
+        return (file_stem(file_name), Some(target.into()));
+    }
+
+    (target.to_lowercase(), None)
+}
+
+/// File name without its extension and version, eg. `ViaVersion-4.9.2.jar` is `viaversion`
+fn file_stem(file_name: &str) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(file_name);
+    let name = stem
+        .split('-')
+        .take_while(|part| !part.starts_with(|c: char| c.is_ascii_digit()))
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if name.is_empty() { stem } else { &name }.to_lowercase()
+}
+
+/// Verifies the checksums published by the source and the ones declared in `crafty.yml`
+fn verify_dependency(
+    file_path: &Path,
+    dependency: &Dependency,
+    resolved: &ResolvedDependency,
+) -> anyhow::Result<()> {
+    if let Some(sha1) = &resolved.sha1 {
+        checksum::verify(file_path, Algorithm::Sha1, sha1)?;
+    }
+    if let Some(sha256) = &resolved.sha256 {
+        checksum::verify(file_path, Algorithm::Sha256, sha256)?;
+    }
+    if let Some(sha512) = &resolved.sha512 {
+        checksum::verify(file_path, Algorithm::Sha512, sha512)?;
+    }
+
+    checksum::verify_declared(
+        file_path,
+        dependency.sha256.as_deref(),
+        dependency.sha512.as_deref(),
+    )
+}
+
+/// Returns the previously installed file if it matches the resolved dependency
+/// and it is still on disk unchanged
+fn installed_file(
+    previous: &DependencyDetails,
+    resolved: &ResolvedDependency,
+    root_directory: &Path,
+    dependencies_directory: &Path,
+) -> anyhow::Result<Option<DependencyFile>> {
+    // Archives are extracted again, they are restored from the cache if they did not change
+    let ([file], None) = (previous.files.as_slice(), &previous.archive) else {
+        return Ok(None);
+    };
+
+    let matches = file.source == resolved.url
+        && previous.version == resolved.version
+        && (resolved.sha256.is_none() || resolved.sha256.as_ref() == Some(&file.hash));
+    let file_path = dependencies_directory.join(&file.filename);
+    let source = installer::local_source(root_directory, &file.source);
+    if !matches || !checksum::is_unchanged(&file_path, &source, &file.hash)? {
+        return Ok(None);
+    }
+
+    Ok(Some(file.clone()))
+}
+
+/// Deletes the files (and directories) in the dependencies directory that do not belong to any dependency
+fn remove_stale_files(
+    dependencies_directory: &Path,
+    dependencies: &HashMap<String, DependencyDetails>,
+) -> anyhow::Result<()> {
+    let Ok(entries) = fs::read_dir(dependencies_directory) else {
+        return Ok(());
+    };
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        // Files extracted from archives are in a directory named after their dependency
+        let is_used = dependencies.iter().any(|(id, details)| {
+            (details.archive.is_some() && *id == file_name)
+                || details.files.iter().any(|file| file.filename == file_name)
+        });
+
+        if is_used {
+            continue;
+        }
+
+        log::info!("Removing unused file \"{}\"...", file_name);
+        if entry.path().is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
     }
 
-    let manifest = DependenciesManifest::new(installed_dependencies);
-    manifest.save_manifest(&root_directory.join(".crafty").join("dependencies.yml"))?;
     Ok(())
 }
 
 async fn install_from_source(
     id: &str,
-    source: &str,
+    resolved: &ResolvedDependency,
+    dependency_type: &DependencyType,
+    root_directory: &Path,
     destination: PathBuf,
+    downloader: &Downloader,
 ) -> anyhow::Result<DependencyFile> {
+    let source = resolved.url.as_str();
+    let file_name = resolved.file_name.as_deref();
     let file_path = if utils::is_url(source) {
-        let filename = utils::get_filename_from_url(source);
+        let filename = file_name
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| utils::get_filename_from_url(source));
         log::info!(
             "Installing \"{}\" ({}) from \"{}\"...",
             id,
@@ -103,10 +773,12 @@ async fn install_from_source(
 
         let destination_file = destination.join(filename);
         fs::create_dir_all(destination_file.parent().unwrap())?;
-        installer::download_file(source.into(), destination_file).await?
+        downloader
+            .download_file(source.into(), destination_file, resolved.sha256.as_deref())
+            .await?
     } else {
         log::info!("Installing \"{}\" from \"{}\"...", id, source);
-        let source = PathBuf::from(source);
+        let source = PathBuf::from(installer::local_source(root_directory, source));
         let fallback_name = format!("{}.jar", id);
         let filename = Path::new(&source)
             .file_name()
@@ -116,11 +788,26 @@ async fn install_from_source(
 
         if !source.exists() {
             return Err(anyhow!("The path \"{}\" does not exist", source.display()));
-        } else if source.is_dir() {
+        } else if source.is_dir()
+            && !matches!(
+                dependency_type,
+                DependencyType::DataPack | DependencyType::Custom
+            )
+        {
             return Err(anyhow!("The path \"{}\" is not a file", source.display()));
         }
 
-        fs::copy(&source, &target_directory)?;
+        fs::create_dir_all(&destination)?;
+        if source.is_dir() {
+            // Directories (data packs or custom dependencies) are copied as is,
+            // removing files deleted from the source
+            if target_directory.exists() {
+                fs::remove_dir_all(&target_directory)?;
+            }
+            utils::copy_directory(&source, &target_directory)?;
+        } else {
+            fs::copy(&source, &target_directory)?;
+        }
         target_directory
     };
 
@@ -133,32 +820,54 @@ async fn install_from_source(
     Ok(DependencyFile {
         filename,
         source: source.into(),
-        hash: "".to_string(),
+        hash: checksum::file_hash(&file_path, Algorithm::Sha256)?,
     })
 }
 
+/// Copies the installed dependencies to the server directory,
+/// removing the files placed by a previous setup that are no longer needed
 pub fn prepare_server_dependencies(
     dependencies: DependenciesManifest,
-    dependencies_directory: &Path,
+    root_directory: &Path,
     server_directory: &Path,
 ) -> anyhow::Result<()> {
+    // Only the dependencies of the selected profile are placed, so they are checked again,
+    // it only warns as every dependency was already validated by the install
+    let server_version = VersionManifest::load_manifest(root_directory)
+        .ok()
+        .and_then(|manifest| manifest.version);
+    if let Err(err) = descriptor::validate(&dependencies.0, server_version.as_deref()) {
+        log::warn!(
+            "The dependencies of the server may not be valid, check the `profiles` of your dependencies ({:#})",
+            err
+        );
+    }
+
+    let dependencies_directory = root_directory.join(".crafty").join("dependencies");
+    let mut resource_packs = vec![];
+    let mut placed_files = vec![];
+
     for (id, dependency) in dependencies.0 {
         log::info!("Preparing dependency {}...", &id);
-        let dependency_files = dependency.files;
         let destination_path = match dependency.dependency_type {
             DependencyType::Mod => server_directory.join("mods"),
             DependencyType::Plugin => server_directory.join("plugins"),
-            _ => {
-                return Err(anyhow!(
-                    "Dependency type {:?} for {} is not yet supported!",
-                    dependency.dependency_type,
-                    id
-                ));
-            }
+            DependencyType::DataPack => datapack::datapacks_directory(server_directory),
+            DependencyType::Library => server_directory.join(LIBRARIES_DIRECTORY),
+            DependencyType::ResourcePack => server_directory.join("resourcepacks"),
+            DependencyType::Custom => match &dependency.target {
+                Some(target) => server_directory.join(target),
+                None => {
+                    return Err(anyhow!(
+                        "The custom dependency \"{}\" has no `target` directory, make sure to run `crafty install` first",
+                        id
+                    ))
+                }
+            },
         };
 
-        for file in dependency_files {
-            let file_path = dependencies_directory.join(file.filename);
+        for file in &dependency.files {
+            let file_path = dependencies_directory.join(&file.filename);
             if !file_path.exists() {
                 return Err(anyhow!(
                     "Dependency \"{}\" was not found, make sure to run `crafty install` first",
@@ -166,13 +875,227 @@ pub fn prepare_server_dependencies(
                 ));
             }
 
-            fs::create_dir_all(&destination_path)?;
-            fs::copy(
-                &file_path,
-                &destination_path.join(file_path.file_name().unwrap()),
-            )?;
+            let destination = destination_path.join(dependency.server_path(&id, file));
+            fs::create_dir_all(destination.parent().unwrap())?;
+            placed_files.push(relative_path(&destination, server_directory));
+            if file_path.is_dir() {
+                if destination.exists() {
+                    fs::remove_dir_all(&destination)?;
+                }
+                utils::copy_directory(&file_path, destination)?;
+            } else {
+                fs::copy(&file_path, destination)?;
+            }
+
+            if dependency.dependency_type == DependencyType::ResourcePack
+                && utils::is_url(&file.source)
+            {
+                resource_packs.push((id.clone(), file.clone(), file_path));
+            }
+        }
+    }
+
+    // Resource packs are sent to players from a URL, only one can be set in `server.properties`
+    resource_packs.sort_by(|(first, ..), (second, ..)| first.cmp(second));
+    if let Some((id, file, file_path)) = resource_packs.first() {
+        if resource_packs.len() > 1 {
+            log::warn!(
+                "Only one resource pack can be sent to players, using \"{}\"",
+                id
+            );
         }
+        set_resource_pack(server_directory, &file.source, file_path)?;
     }
 
-    Ok(())
+    remove_stale_server_files(root_directory, server_directory, placed_files)
+}
+
+/// Removes the files placed in the server directory by the previous setup that were not placed again,
+/// the placed files are saved in `.crafty/server-state.yml`
+fn remove_stale_server_files(
+    root_directory: &Path,
+    server_directory: &Path,
+    mut placed_files: Vec<String>,
+) -> anyhow::Result<()> {
+    let mut state = ServerState::load_manifest(root_directory).unwrap_or_default();
+    let server_key = relative_path(server_directory, root_directory);
+    placed_files.sort();
+    placed_files.dedup();
+
+    let previous_files = state.0.remove(&server_key).unwrap_or_default();
+    for file in previous_files {
+        let path = server_directory.join(&file);
+        if placed_files.contains(&file) || !path.exists() {
+            continue;
+        }
+
+        log::info!("Removing stale file \"{}\"...", file);
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    state.0.insert(server_key, placed_files);
+    state.save_manifest(&root_directory.join(".crafty").join("server-state.yml"))
+}
+
+/// Path relative to a directory with `/` separators, or the full path if it is outside the directory
+fn relative_path(path: &Path, directory: &Path) -> String {
+    path.strip_prefix(directory)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Sets the `resource-pack` and `resource-pack-sha1` properties of the `server.properties` file,
+/// the file is left untouched if they are already set, so the other properties are kept as they are
+fn set_resource_pack(server_directory: &Path, url: &str, file_path: &Path) -> anyhow::Result<()> {
+    let properties_path = server_directory.join("server.properties");
+    let properties = fs::read_to_string(&properties_path).unwrap_or_default();
+    let sha1 = checksum::file_hash(file_path, Algorithm::Sha1)?;
+    let values = [
+        ("resource-pack", url),
+        ("resource-pack-sha1", sha1.as_str()),
+    ];
+
+    let mut lines: Vec<String> = properties.lines().map(String::from).collect();
+    let mut changed = false;
+    for (key, value) in values {
+        let line = format!("{}={}", key, value);
+        let existing = lines
+            .iter()
+            .position(|line| parse_property(line).is_some_and(|(name, _)| name == key));
+
+        match existing {
+            Some(index)
+                if parse_property(&lines[index]).is_some_and(|(_, current)| current == value) => {}
+            Some(index) => {
+                lines[index] = line;
+                changed = true;
+            }
+            None => {
+                lines.push(line);
+                changed = true;
+            }
+        }
+    }
+
+    if !changed {
+        return Ok(());
+    }
+
+    fs::write(&properties_path, lines.join("\n") + "\n")
+        .context("Could not set the resource pack in \"server.properties\"")
+}
+
+/// Key and value of a `server.properties` line, the server escapes `:` and `=` when it saves the file
+fn parse_property(line: &str) -> Option<(&str, String)> {
+    let (key, value) = line.split_once('=')?;
+    Some((
+        key.trim(),
+        value.trim().replace("\\:", ":").replace("\\=", "="),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(target: &str) -> (String, Option<String>) {
+        detect_source(target, Path::new("/nonexistent"))
+    }
+
+    #[test]
+    fn detects_prefixed_sources() {
+        assert_eq!(
+            detect("hangar:ViaVersion/ViaVersion"),
+            (
+                "viaversion".into(),
+                Some("hangar:ViaVersion/ViaVersion".into())
+            )
+        );
+        assert_eq!(
+            detect("maven:net.luckperms:api:5.4"),
+            ("api".into(), Some("maven:net.luckperms:api:5.4".into()))
+        );
+    }
+
+    #[test]
+    fn detects_project_pages() {
+        assert_eq!(
+            detect("https://modrinth.com/plugin/LuckPerms/versions"),
+            ("luckperms".into(), Some("modrinth:LuckPerms".into()))
+        );
+        assert_eq!(
+            detect("https://hangar.papermc.io/ViaVersion/ViaVersion"),
+            (
+                "viaversion".into(),
+                Some("hangar:ViaVersion/ViaVersion".into())
+            )
+        );
+        assert_eq!(
+            detect("https://github.com/lucko/spark/releases"),
+            ("spark".into(), Some("github:lucko/spark".into()))
+        );
+        assert_eq!(
+            detect("https://ci.example.com/job/Geyser/"),
+            (
+                "geyser".into(),
+                Some("jenkins:https://ci.example.com/job/Geyser".into())
+            )
+        );
+    }
+
+    #[test]
+    fn detects_files() {
+        assert_eq!(
+            detect("https://example.com/files/ViaVersion-4.9.2.jar"),
+            (
+                "viaversion".into(),
+                Some("https://example.com/files/ViaVersion-4.9.2.jar".into())
+            )
+        );
+
+        let directory = tempfile::tempdir().unwrap();
+        fs::create_dir(directory.path().join("jars")).unwrap();
+        fs::write(directory.path().join("jars/my-plugin-1.0.jar"), "").unwrap();
+        assert_eq!(
+            detect_source("jars/my-plugin-1.0.jar", directory.path()),
+            ("my-plugin".into(), Some("jars/my-plugin-1.0.jar".into()))
+        );
+    }
+
+    #[test]
+    fn defaults_to_modrinth_slugs() {
+        assert_eq!(detect("LuckPerms"), ("luckperms".into(), None));
+    }
+
+    #[test]
+    fn sets_the_resource_pack_only_if_it_changed() {
+        let directory = tempfile::tempdir().unwrap();
+        let pack = directory.path().join("pack.zip");
+        fs::write(&pack, "pack").unwrap();
+        let sha1 = checksum::file_hash(&pack, Algorithm::Sha1).unwrap();
+        let properties = directory.path().join("server.properties");
+
+        // Values escaped by the server are not rewritten
+        let saved = format!(
+            "#Minecraft server properties\nmotd=Hello\nresource-pack=https\\://example.com/pack.zip\nresource-pack-sha1={}\n",
+            sha1
+        );
+        fs::write(&properties, &saved).unwrap();
+        set_resource_pack(directory.path(), "https://example.com/pack.zip", &pack).unwrap();
+        assert_eq!(fs::read_to_string(&properties).unwrap(), saved);
+
+        set_resource_pack(directory.path(), "https://example.com/new.zip", &pack).unwrap();
+        assert_eq!(
+            fs::read_to_string(&properties).unwrap(),
+            format!(
+                "#Minecraft server properties\nmotd=Hello\nresource-pack=https://example.com/new.zip\nresource-pack-sha1={}\n",
+                sha1
+            )
+        );
+    }
 }
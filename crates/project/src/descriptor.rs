@@ -0,0 +1,629 @@
+//! Descriptors of plugins and mods (eg. `plugin.yml` or `fabric.mod.json`) read from their JAR files
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use zip::ZipArchive;
+
+use crate::manifests::DependencyDetails;
+use crate::Server;
+
+/// Ids of the dependencies mods can declare on the server itself
+const PLATFORM_IDS: &[&str] = &[
+    "minecraft",
+    "java",
+    "fabricloader",
+    "fabric-loader",
+    "forge",
+    "neoforge",
+    "javafml",
+];
+
+/// Directories mod loaders load nested JAR files from (jar-in-jar), eg. the modules of Fabric API
+const NESTED_JAR_DIRECTORIES: &[&str] = &["META-INF/jars/", "META-INF/jarjar/"];
+/// Maximum depth of nested JAR files that are read
+const MAX_NESTED_DEPTH: usize = 2;
+
+/// Information declared by a plugin or a mod in its descriptor file
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Descriptor {
+    /// Name (or id) other plugins or mods use to depend on it
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Minimum Minecraft version of the Bukkit API, for Bukkit and Paper plugins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    /// Dependencies required to load it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depend: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub softdepend: Vec<String>,
+    /// Other names it can be depended on with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provides: Vec<String>,
+    /// Platform of the descriptor, eg. `bukkit`, `paper`, `velocity` or `fabric`
+    pub loader: String,
+}
+
+/// Descriptor files and their loader, proxies are looked up first on proxy servers
+/// as some JAR files support multiple platforms
+fn descriptor_files(server: &Server) -> Vec<(&'static str, &'static str)> {
+    let servers = [
+        ("paper-plugin.yml", "paper"),
+        ("plugin.yml", "bukkit"),
+        ("fabric.mod.json", "fabric"),
+        ("META-INF/neoforge.mods.toml", "neoforge"),
+        ("META-INF/mods.toml", "forge"),
+    ];
+    let proxies = [
+        ("velocity-plugin.json", "velocity"),
+        ("bungee.yml", "bungeecord"),
+    ];
+
+    let brand = server.brand.as_deref().unwrap_or_default().to_lowercase();
+    match brand.as_str() {
+        "velocity" | "waterfall" | "bungeecord" => proxies.into_iter().chain(servers).collect(),
+        _ => servers.into_iter().chain(proxies).collect(),
+    }
+}
+
+/// Reads the descriptor of a plugin or mod JAR file, `None` if it has none
+pub(crate) fn read(path: &Path, server: &Server) -> anyhow::Result<Option<Descriptor>> {
+    let mut archive = ZipArchive::new(File::open(path)?)
+        .with_context(|| format!("\"{}\" is not a JAR file", path.display()))?;
+
+    read_archive(&mut archive, server, 0)
+}
+
+fn read_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    server: &Server,
+    depth: usize,
+) -> anyhow::Result<Option<Descriptor>> {
+    for (file_name, loader) in descriptor_files(server) {
+        let mut contents = String::new();
+        match archive.by_name(file_name) {
+            Ok(mut file) => file.read_to_string(&mut contents)?,
+            Err(_) => continue,
+        };
+
+        let mut descriptor = match loader {
+            "paper" | "bukkit" | "bungeecord" => parse_yaml(&contents, loader),
+            "velocity" | "fabric" => parse_json(&contents, loader),
+            _ => parse_mods_toml(&contents, loader, archive),
+        }
+        .with_context(|| format!("Could not parse \"{}\"", file_name))?;
+
+        // Mods bundled in the JAR file can be depended on as well
+        if matches!(loader, "fabric" | "forge" | "neoforge") && depth < MAX_NESTED_DEPTH {
+            for nested in nested_descriptors(archive, server, depth + 1) {
+                for name in std::iter::once(nested.name).chain(nested.provides) {
+                    if name != descriptor.name && !descriptor.provides.contains(&name) {
+                        descriptor.provides.push(name);
+                    }
+                }
+            }
+        }
+
+        return Ok(Some(descriptor));
+    }
+
+    Ok(None)
+}
+
+/// Descriptors of the JAR files nested in a mod, invalid ones are ignored
+fn nested_descriptors<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    server: &Server,
+    depth: usize,
+) -> Vec<Descriptor> {
+    let nested_jars: Vec<String> = archive
+        .file_names()
+        .filter(|name| {
+            name.ends_with(".jar")
+                && NESTED_JAR_DIRECTORIES
+                    .iter()
+                    .any(|directory| name.starts_with(directory))
+        })
+        .map(String::from)
+        .collect();
+
+    nested_jars
+        .iter()
+        .filter_map(|name| {
+            let mut contents = vec![];
+            archive
+                .by_name(name)
+                .ok()?
+                .read_to_end(&mut contents)
+                .ok()?;
+            let mut nested = ZipArchive::new(Cursor::new(contents)).ok()?;
+            read_archive(&mut nested, server, depth).ok().flatten()
+        })
+        .collect()
+}
+
+fn parse_yaml(contents: &str, loader: &str) -> anyhow::Result<Descriptor> {
+    let descriptor: YamlValue = serde_yaml::from_str(contents)?;
+    let field = |key: &str| descriptor.get(key).cloned().unwrap_or_default();
+
+    let (depend, softdepend) = match loader {
+        "bungeecord" => (
+            yaml_list(&field("depends")),
+            yaml_list(&field("softDepends")),
+        ),
+        // Paper plugins declare `dependencies` for the server (and bootstrap), required by default
+        "paper" if field("depend").is_null() => {
+            let mut depend = vec![];
+            let mut softdepend = vec![];
+            let dependencies = field("dependencies");
+            let entries: Vec<(String, &YamlValue)> = match &dependencies {
+                YamlValue::Mapping(_) => dependencies
+                    .get("server")
+                    .and_then(YamlValue::as_mapping)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(name, entry)| Some((yaml_string(name)?, entry)))
+                    .collect(),
+                YamlValue::Sequence(entries) => entries
+                    .iter()
+                    .filter_map(|entry| Some((yaml_string(entry.get("name")?)?, entry)))
+                    .collect(),
+                _ => vec![],
+            };
+
+            for (name, entry) in entries {
+                match entry.get("required").and_then(YamlValue::as_bool) {
+                    Some(false) => softdepend.push(name),
+                    _ => depend.push(name),
+                }
+            }
+            (depend, softdepend)
+        }
+        _ => (yaml_list(&field("depend")), yaml_list(&field("softdepend"))),
+    };
+
+    Ok(Descriptor {
+        name: yaml_string(&field("name")).ok_or_else(|| anyhow!("The name is missing"))?,
+        version: yaml_string(&field("version")),
+        api_version: yaml_string(&field("api-version")),
+        depend,
+        softdepend,
+        provides: yaml_list(&field("provides")),
+        loader: loader.into(),
+    })
+}
+
+fn parse_json(contents: &str, loader: &str) -> anyhow::Result<Descriptor> {
+    let descriptor: JsonValue = serde_json::from_str(contents)?;
+    let field = |key: &str| descriptor.get(key).cloned().unwrap_or_default();
+
+    let (depend, softdepend, provides) = match loader {
+        "velocity" => {
+            let dependencies = field("dependencies");
+            let (optional, required): (Vec<&JsonValue>, Vec<&JsonValue>) = dependencies
+                .as_array()
+                .into_iter()
+                .flatten()
+                .partition(|dependency| dependency["optional"].as_bool().unwrap_or_default());
+            let ids = |dependencies: Vec<&JsonValue>| {
+                dependencies
+                    .iter()
+                    .filter_map(|dependency| json_string(&dependency["id"]))
+                    .collect()
+            };
+            (ids(required), ids(optional), vec![])
+        }
+        _ => {
+            let ids = |key: &str| -> Vec<String> {
+                field(key)
+                    .as_object()
+                    .into_iter()
+                    .flat_map(|dependencies| dependencies.keys().cloned())
+                    .filter(|id| !PLATFORM_IDS.contains(&id.as_str()))
+                    .collect()
+            };
+            let mut softdepend = ids("recommends");
+            softdepend.extend(ids("suggests"));
+            let provides = field("provides")
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(json_string)
+                .collect();
+            (ids("depends"), softdepend, provides)
+        }
+    };
+
+    Ok(Descriptor {
+        name: json_string(&field("id")).ok_or_else(|| anyhow!("The id is missing"))?,
+        version: json_string(&field("version")),
+        api_version: None,
+        depend,
+        softdepend,
+        provides,
+        loader: loader.into(),
+    })
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ModsToml {
+    #[serde(default)]
+    mods: Vec<ModEntry>,
+    #[serde(default)]
+    dependencies: HashMap<String, Vec<ModDependency>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ModEntry {
+    mod_id: String,
+    version: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ModDependency {
+    mod_id: String,
+    /// Used by Forge
+    mandatory: Option<bool>,
+    /// Used by NeoForge, `required`, `optional`, `incompatible` or `discouraged`
+    #[serde(rename = "type")]
+    dependency_type: Option<String>,
+}
+
+fn parse_mods_toml<R: Read + Seek>(
+    contents: &str,
+    loader: &str,
+    archive: &mut ZipArchive<R>,
+) -> anyhow::Result<Descriptor> {
+    let descriptor: ModsToml = toml::from_str(contents)?;
+    let entry = descriptor
+        .mods
+        .first()
+        .ok_or_else(|| anyhow!("No mod is declared"))?;
+
+    // The version is usually replaced with the one of the JAR manifest when the mod is loaded
+    let version = match entry.version.as_deref() {
+        Some("${file.jarVersion}") => {
+            let mut manifest = String::new();
+            archive
+                .by_name("META-INF/MANIFEST.MF")
+                .ok()
+                .and_then(|mut file| file.read_to_string(&mut manifest).ok());
+            manifest
+                .lines()
+                .find_map(|line| line.strip_prefix("Implementation-Version:"))
+                .map(|version| version.trim().to_string())
+        }
+        version => version.map(String::from),
+    };
+
+    let mut depend = vec![];
+    let mut softdepend = vec![];
+    for dependency in descriptor
+        .dependencies
+        .get(&entry.mod_id)
+        .into_iter()
+        .flatten()
+        .filter(|dependency| !PLATFORM_IDS.contains(&dependency.mod_id.as_str()))
+    {
+        match (dependency.mandatory, dependency.dependency_type.as_deref()) {
+            (Some(true), _) | (None, Some("required")) => depend.push(dependency.mod_id.clone()),
+            (Some(false), _) | (None, Some("optional")) => {
+                softdepend.push(dependency.mod_id.clone())
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Descriptor {
+        name: entry.mod_id.clone(),
+        version,
+        api_version: None,
+        depend,
+        softdepend,
+        provides: vec![],
+        loader: loader.into(),
+    })
+}
+
+fn yaml_string(value: &YamlValue) -> Option<String> {
+    match value {
+        YamlValue::String(value) => Some(value.clone()),
+        YamlValue::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// A list of strings, which can also be written as a single string
+fn yaml_list(value: &YamlValue) -> Vec<String> {
+    match value {
+        YamlValue::Sequence(values) => values.iter().filter_map(yaml_string).collect(),
+        value => yaml_string(value).into_iter().collect(),
+    }
+}
+
+fn json_string(value: &JsonValue) -> Option<String> {
+    value.as_str().map(String::from)
+}
+
+/// Checks that the hard dependencies of every plugin and mod are installed,
+/// and warns about plugins made for a newer version than the server
+pub(crate) fn validate(
+    dependencies: &HashMap<String, DependencyDetails>,
+    server_version: Option<&str>,
+) -> anyhow::Result<()> {
+    // Bukkit replaces spaces in plugin names with underscores
+    let normalize = |name: &str| name.replace(' ', "_");
+    let names: HashSet<String> = dependencies
+        .values()
+        .filter_map(|details| details.descriptor.as_ref())
+        .flat_map(|descriptor| std::iter::once(&descriptor.name).chain(&descriptor.provides))
+        .map(|name| normalize(name))
+        .collect();
+
+    let mut ids: Vec<&String> = dependencies.keys().collect();
+    ids.sort();
+
+    let mut missing = vec![];
+    for id in ids {
+        let Some(descriptor) = &dependencies[id].descriptor else {
+            continue;
+        };
+
+        for depend in &descriptor.depend {
+            if !names.contains(&normalize(depend)) {
+                missing.push(format!("\"{}\" requires \"{}\"", id, depend));
+            }
+        }
+
+        if let (Some(api_version), Some(server_version)) = (&descriptor.api_version, server_version)
+        {
+            if is_newer(api_version, server_version) {
+                log::warn!(
+                    "\"{}\" is made for Minecraft {} (api-version) but the server version is {}",
+                    id,
+                    api_version,
+                    server_version
+                );
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Some dependencies are missing, add them to \"crafty.yml\":\n  - {}",
+            missing.join("\n  - ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether an `api-version` (eg. `1.20`) is newer than a server version (eg. `1.19.4`),
+/// `false` if one of them is not a release version
+fn is_newer(api_version: &str, server_version: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u32>> {
+        version.split('.').map(|part| part.parse().ok()).collect()
+    };
+    let (Some(api_version), Some(server_version)) = (parse(api_version), parse(server_version))
+    else {
+        return false;
+    };
+
+    // An `api-version` of `1.20` supports any `1.20.x` server
+    let server_version: Vec<u32> = (0..api_version.len())
+        .map(|index| server_version.get(index).copied().unwrap_or_default())
+        .collect();
+    api_version > server_version
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+    use crate::dependencies::DependencyType;
+
+    fn server(brand: &str) -> Server {
+        serde_yaml::from_str(&format!("brand: {}", brand)).unwrap()
+    }
+
+    fn jar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        for (name, contents) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn read_jar(files: &[(&str, &[u8])], brand: &str) -> Option<Descriptor> {
+        let mut archive = ZipArchive::new(Cursor::new(jar(files))).unwrap();
+        read_archive(&mut archive, &server(brand), 0).unwrap()
+    }
+
+    #[test]
+    fn parses_bukkit_plugins() {
+        let descriptor = parse_yaml(
+            "name: My Plugin\nversion: 1.0\napi-version: '1.20'\ndepend: Vault\nsoftdepend: [LuckPerms]",
+            "bukkit",
+        )
+        .unwrap();
+
+        assert_eq!(descriptor.name, "My Plugin");
+        assert_eq!(descriptor.version.as_deref(), Some("1.0"));
+        assert_eq!(descriptor.api_version.as_deref(), Some("1.20"));
+        assert_eq!(descriptor.depend, vec!["Vault"]);
+        assert_eq!(descriptor.softdepend, vec!["LuckPerms"]);
+        assert!(parse_yaml("version: 1.0", "bukkit").is_err());
+    }
+
+    #[test]
+    fn parses_paper_plugin_dependencies() {
+        let descriptor = parse_yaml(
+            "name: Alpha
+dependencies:
+  bootstrap:
+    Ignored: {}
+  server:
+    Vault: {}
+    LuckPerms:
+      required: false",
+            "paper",
+        )
+        .unwrap();
+
+        assert_eq!(descriptor.depend, vec!["Vault"]);
+        assert_eq!(descriptor.softdepend, vec!["LuckPerms"]);
+    }
+
+    #[test]
+    fn parses_fabric_and_velocity_mods() {
+        let fabric = parse_json(
+            r#"{"id": "mymod", "version": "2.0", "depends": {"minecraft": "1.20.x", "fabric-api": "*"},
+                "suggests": {"modmenu": "*"}, "provides": ["my_mod"]}"#,
+            "fabric",
+        )
+        .unwrap();
+        assert_eq!(fabric.name, "mymod");
+        assert_eq!(fabric.depend, vec!["fabric-api"]);
+        assert_eq!(fabric.softdepend, vec!["modmenu"]);
+        assert_eq!(fabric.provides, vec!["my_mod"]);
+
+        let velocity = parse_json(
+            r#"{"id": "proxy", "dependencies": [{"id": "luckperms"}, {"id": "geyser", "optional": true}]}"#,
+            "velocity",
+        )
+        .unwrap();
+        assert_eq!(velocity.depend, vec!["luckperms"]);
+        assert_eq!(velocity.softdepend, vec!["geyser"]);
+    }
+
+    #[test]
+    fn parses_mods_toml_with_the_jar_version() {
+        let descriptor = read_jar(
+            &[
+                (
+                    "META-INF/neoforge.mods.toml",
+                    br#"
+[[mods]]
+modId = "mymod"
+version = "${file.jarVersion}"
+
+[[dependencies.mymod]]
+modId = "neoforge"
+type = "required"
+
+[[dependencies.mymod]]
+modId = "curios"
+type = "required"
+
+[[dependencies.mymod]]
+modId = "jei"
+type = "optional"
+"#,
+                ),
+                (
+                    "META-INF/MANIFEST.MF",
+                    b"Manifest-Version: 1.0\nImplementation-Version: 3.1\n",
+                ),
+            ],
+            "neoforge",
+        )
+        .unwrap();
+
+        assert_eq!(descriptor.name, "mymod");
+        assert_eq!(descriptor.version.as_deref(), Some("3.1"));
+        assert_eq!(descriptor.depend, vec!["curios"]);
+        assert_eq!(descriptor.softdepend, vec!["jei"]);
+        assert_eq!(descriptor.loader, "neoforge");
+    }
+
+    #[test]
+    fn prefers_proxy_descriptors_on_proxies() {
+        let files: &[(&str, &[u8])] = &[
+            ("plugin.yml", b"name: Bukkit"),
+            ("velocity-plugin.json", br#"{"id": "velocity"}"#),
+        ];
+
+        assert_eq!(read_jar(files, "paper").unwrap().loader, "bukkit");
+        assert_eq!(read_jar(files, "velocity").unwrap().loader, "velocity");
+        assert!(read_jar(&[("README.md", b"")], "paper").is_none());
+    }
+
+    #[test]
+    fn nested_jars_are_provided() {
+        let module = jar(&[("fabric.mod.json", br#"{"id": "fabric-api-base"}"#)]);
+        let descriptor = read_jar(
+            &[
+                ("fabric.mod.json", br#"{"id": "fabric-api"}"#),
+                ("META-INF/jars/fabric-api-base.jar", &module),
+                ("META-INF/jars/invalid.jar", b"not a jar"),
+            ],
+            "fabric",
+        )
+        .unwrap();
+
+        assert_eq!(descriptor.name, "fabric-api");
+        assert_eq!(descriptor.provides, vec!["fabric-api-base"]);
+    }
+
+    fn details(descriptor: Descriptor) -> DependencyDetails {
+        DependencyDetails {
+            dependency_type: DependencyType::Plugin,
+            target: None,
+            version: None,
+            archive: None,
+            files: vec![],
+            descriptor: Some(descriptor),
+            profiles: vec![],
+        }
+    }
+
+    #[test]
+    fn validates_hard_dependencies() {
+        let plugin = |name: &str, depend: &[&str]| {
+            details(Descriptor {
+                name: name.into(),
+                depend: depend.iter().map(|depend| depend.to_string()).collect(),
+                loader: "bukkit".into(),
+                ..Default::default()
+            })
+        };
+        let mut dependencies = HashMap::from([
+            ("alpha".to_string(), plugin("Alpha", &["Beta Plugin"])),
+            ("beta".to_string(), plugin("Beta_Plugin", &[])),
+        ]);
+        assert!(validate(&dependencies, Some("1.20.4")).is_ok());
+
+        dependencies.insert("gamma".into(), plugin("Gamma", &["Vault"]));
+        let err = validate(&dependencies, None).unwrap_err();
+        assert!(
+            err.to_string().contains("\"gamma\" requires \"Vault\""),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn compares_api_versions() {
+        assert!(is_newer("1.21", "1.20.4"));
+        assert!(is_newer("1.20.5", "1.20.4"));
+        assert!(!is_newer("1.20", "1.20.4"));
+        assert!(!is_newer("1.13", "1.20"));
+        assert!(!is_newer("1.21", "24w03a"));
+    }
+}
@@ -0,0 +1,341 @@
+//! Line based edits of the `crafty.yml` file, so comments and key order are kept
+
+use anyhow::anyhow;
+
+const DEPENDENCIES_KEY: &str = "dependencies:";
+
+/// Adds a dependency entry with the given properties at the end of the `dependencies` section
+pub(crate) fn add_dependency(
+    contents: &str,
+    id: &str,
+    properties: &[(&str, serde_yaml::Value)],
+) -> anyhow::Result<String> {
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+
+    let (start, end) = match dependencies_section(&lines)? {
+        Some(section) => section,
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(DEPENDENCIES_KEY.into());
+            (lines.len() - 1, lines.len())
+        }
+    };
+
+    if lines[start][DEPENDENCIES_KEY.len()..].trim() == "{}" {
+        lines[start] = DEPENDENCIES_KEY.into();
+    }
+
+    let indent = entry_indent(&lines[start + 1..end]);
+    if find_entry(&lines, start, end, &indent, id).is_some() {
+        return Err(anyhow!("The dependency \"{}\" already exists", id));
+    }
+
+    // Insert after the last line of the section that belongs to it, keeping trailing blank lines
+    let insert_at = (start + 1..end)
+        .rev()
+        .find(|index| is_indented(&lines[*index]))
+        .map(|index| index + 1)
+        .unwrap_or(start + 1);
+
+    let mut entry = vec![format!("{}{}:", indent, scalar(id))];
+    for (key, value) in properties {
+        entry.push(format!(
+            "{}{}{}: {}",
+            indent,
+            indent,
+            key,
+            format_value(value)?
+        ));
+    }
+    lines.splice(insert_at..insert_at, entry);
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Removes a dependency entry and the comments right above it
+pub(crate) fn remove_dependency(contents: &str, id: &str) -> anyhow::Result<String> {
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+    let not_found = || anyhow!("The dependency \"{}\" was not found in \"crafty.yml\"", id);
+
+    let (start, end) = dependencies_section(&lines)?.ok_or_else(not_found)?;
+    let indent = entry_indent(&lines[start + 1..end]);
+    let entry = find_entry(&lines, start, end, &indent, id).ok_or_else(not_found)?;
+
+    let mut entry_end = (entry + 1..end)
+        .find(|index| is_key(&lines[*index]) && indent_of(&lines[*index]) <= indent.len())
+        .unwrap_or(end);
+    // Blank lines and comments before the next entry belong to it
+    while entry_end > entry + 1 && !is_key(&lines[entry_end - 1]) {
+        if indent_of(&lines[entry_end - 1]) > indent.len() && is_comment(&lines[entry_end - 1]) {
+            break;
+        }
+        entry_end -= 1;
+    }
+
+    let mut entry_start = entry;
+    while entry_start > start + 1
+        && is_comment(&lines[entry_start - 1])
+        && indent_of(&lines[entry_start - 1]) == indent.len()
+    {
+        entry_start -= 1;
+    }
+
+    lines.drain(entry_start..entry_end);
+    if entry_start < lines.len()
+        && lines[entry_start].trim().is_empty()
+        && lines[entry_start - 1].trim().is_empty()
+    {
+        lines.remove(entry_start);
+    }
+
+    // An empty section would be parsed as `null`
+    if !lines[start + 1..]
+        .iter()
+        .take_while(|line| !is_key(line) || is_indented(line))
+        .any(|line| is_key(line))
+    {
+        lines[start] = format!("{} {{}}", DEPENDENCIES_KEY);
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Sets the value of a nested property (eg. `["server", "build"]`), keeping its trailing comment,
+/// the property is added at the end of its parent mapping if it does not exist
+pub(crate) fn set_property(
+    contents: &str,
+    path: &[&str],
+    value: &serde_yaml::Value,
+) -> anyhow::Result<String> {
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+    let value = format_value(value)?;
+    let (last, parents) = path
+        .split_last()
+        .ok_or_else(|| anyhow!("No property to set"))?;
+
+    let (mut start, mut end, mut indent) = (0, lines.len(), String::new());
+    for key in parents {
+        let parent = find_key(&lines, start, end, &indent, key)
+            .ok_or_else(|| anyhow!("Could not find \"{}\" in \"crafty.yml\"", path.join(".")))?;
+        end = (parent + 1..end)
+            .find(|index| is_key(&lines[*index]) && indent_of(&lines[*index]) <= indent.len())
+            .unwrap_or(end);
+        start = parent + 1;
+        indent = lines[start..end]
+            .iter()
+            .find(|line| is_key(line))
+            .map(|line| line[..indent_of(line)].to_string())
+            .unwrap_or_else(|| format!("{}  ", indent));
+    }
+
+    match find_key(&lines, start, end, &indent, last) {
+        Some(index) => {
+            let line = &lines[index];
+            let comment = line
+                .find(" #")
+                .filter(|position| *position > line.find(':').unwrap_or_default())
+                .map(|position| line[position..].to_string())
+                .unwrap_or_default();
+            lines[index] = format!("{}{}: {}{}", indent, last, value, comment);
+        }
+        None => {
+            let insert_at = (start..end)
+                .rev()
+                .find(|index| is_key(&lines[*index]))
+                .map(|index| index + 1)
+                .unwrap_or(start);
+            lines.insert(insert_at, format!("{}{}: {}", indent, last, value));
+        }
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Returns the line of the `dependencies` key and the end of its section (exclusive)
+fn dependencies_section(lines: &[String]) -> anyhow::Result<Option<(usize, usize)>> {
+    let Some(start) = lines
+        .iter()
+        .position(|line| line.starts_with(DEPENDENCIES_KEY))
+    else {
+        return Ok(None);
+    };
+
+    let value = lines[start][DEPENDENCIES_KEY.len()..].trim();
+    if value == "{}" {
+        return Ok(Some((start, start + 1)));
+    } else if !value.is_empty() && !value.starts_with('#') {
+        return Err(anyhow!(
+            "The \"dependencies\" property in \"crafty.yml\" must be a block mapping to be edited"
+        ));
+    }
+
+    let end = (start + 1..lines.len())
+        .find(|index| is_key(&lines[*index]) && !is_indented(&lines[*index]))
+        .unwrap_or(lines.len());
+
+    Ok(Some((start, end)))
+}
+
+fn find_entry(lines: &[String], start: usize, end: usize, indent: &str, id: &str) -> Option<usize> {
+    find_key(lines, start + 1, end, indent, id)
+}
+
+/// Finds the line of a key at the given indentation within a range of lines
+fn find_key(lines: &[String], start: usize, end: usize, indent: &str, id: &str) -> Option<usize> {
+    (start..end).find(|index| {
+        let line = &lines[*index];
+        indent_of(line) == indent.len()
+            && is_key(line)
+            && line
+                .trim()
+                .split_once(':')
+                .is_some_and(|(key, _)| key.trim_matches(|c| c == '"' || c == '\'') == id)
+    })
+}
+
+/// Indentation used by the entries of a section, defaults to two spaces
+fn entry_indent(section: &[String]) -> String {
+    section
+        .iter()
+        .find(|line| is_key(line))
+        .map(|line| line[..indent_of(line)].to_string())
+        .filter(|indent| !indent.is_empty())
+        .unwrap_or_else(|| "  ".into())
+}
+
+fn format_value(value: &serde_yaml::Value) -> anyhow::Result<String> {
+    match value {
+        serde_yaml::Value::String(value) => Ok(scalar(value)),
+        value => Ok(serde_yaml::to_string(value)?.trim_end().to_string()),
+    }
+}
+
+/// Formats a value as a plain YAML scalar, or a quoted one if it would be parsed as something else
+fn scalar(value: &str) -> String {
+    let is_plain = matches!(
+        serde_yaml::from_str::<serde_yaml::Value>(value),
+        Ok(serde_yaml::Value::String(parsed)) if parsed == value
+    );
+
+    if is_plain && !value.contains(" #") && !value.contains(": ") {
+        value.to_string()
+    } else {
+        serde_json::to_string(value).unwrap()
+    }
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_indented(line: &str) -> bool {
+    !line.trim().is_empty() && indent_of(line) > 0
+}
+
+fn is_comment(line: &str) -> bool {
+    line.trim_start().starts_with('#')
+}
+
+/// Whether the line has content other than blanks or comments
+fn is_key(line: &str) -> bool {
+    !line.trim().is_empty() && !is_comment(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "\
+name: test
+server:
+  brand: paper
+  version: 1.20.4 # pinned
+  build: 400
+
+dependencies:
+  # Permissions
+  luckperms:
+    version: 5.4.0
+  vault:
+    source: jars/vault.jar
+
+settings:
+  port: 25565
+";
+
+    fn parse(contents: &str) -> serde_yaml::Value {
+        serde_yaml::from_str(contents).unwrap()
+    }
+
+    #[test]
+    fn adds_dependencies_at_the_end_of_the_section() {
+        let properties = [("version", "latest".into()), ("required", true.into())];
+        let contents = add_dependency(MANIFEST, "spark", &properties).unwrap();
+
+        assert!(contents.contains(
+            "    source: jars/vault.jar\n  spark:\n    version: latest\n    required: true\n\nsettings:"
+        ));
+        assert!(contents.contains("  # Permissions\n  luckperms:"));
+        assert_eq!(parse(&contents)["dependencies"]["spark"]["required"], true);
+    }
+
+    #[test]
+    fn adds_the_section_if_missing_or_empty() {
+        let contents = add_dependency("name: test\n", "spark", &[]).unwrap();
+        assert_eq!(contents, "name: test\n\ndependencies:\n  spark:\n");
+
+        let contents = add_dependency("dependencies: {}\n", "spark", &[]).unwrap();
+        assert_eq!(contents, "dependencies:\n  spark:\n");
+    }
+
+    #[test]
+    fn quotes_values_that_are_not_plain_strings() {
+        let properties = [("version", "1.0".into()), ("source", "a: b".into())];
+        let contents = add_dependency(MANIFEST, "true", &properties).unwrap();
+
+        assert!(contents.contains("  \"true\":\n    version: \"1.0\"\n    source: \"a: b\"\n"));
+        assert_eq!(parse(&contents)["dependencies"]["true"]["version"], "1.0");
+    }
+
+    #[test]
+    fn fails_to_add_existing_dependencies() {
+        assert!(add_dependency(MANIFEST, "vault", &[]).is_err());
+        assert!(add_dependency("dependencies: {a: {}}\n", "b", &[]).is_err());
+    }
+
+    #[test]
+    fn removes_dependencies_with_their_comments() {
+        let contents = remove_dependency(MANIFEST, "luckperms").unwrap();
+        assert!(
+            contents.contains("dependencies:\n  vault:\n    source: jars/vault.jar\n\nsettings:")
+        );
+        assert!(!contents.contains("Permissions"));
+
+        let contents = remove_dependency(&contents, "vault").unwrap();
+        assert!(contents.contains("dependencies: {}\n\nsettings:"));
+        assert!(parse(&contents)["dependencies"]
+            .as_mapping()
+            .unwrap()
+            .is_empty());
+
+        assert!(remove_dependency(&contents, "vault").is_err());
+    }
+
+    #[test]
+    fn sets_properties_keeping_comments() {
+        let contents = set_property(MANIFEST, &["server", "version"], &"1.20.6".into()).unwrap();
+        assert!(contents.contains("  version: 1.20.6 # pinned\n"));
+
+        let contents = set_property(&contents, &["server", "build"], &500.into()).unwrap();
+        assert!(contents.contains("  build: 500\n"));
+
+        let path = ["dependencies", "vault", "version"];
+        let contents = set_property(&contents, &path, &"1.7".into()).unwrap();
+        assert!(contents.contains("    source: jars/vault.jar\n    version: \"1.7\"\n"));
+        assert_eq!(contents.lines().count(), MANIFEST.lines().count() + 1);
+
+        assert!(set_property(MANIFEST, &["missing", "version"], &1.into()).is_err());
+    }
+}
@@ -0,0 +1,493 @@
+//! Graph of the dependencies between the plugins and mods of a project, built from their descriptors
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+
+use crate::load_project;
+use crate::manifests::DependenciesManifest;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Node {
+    /// Dependency id, or the name of a missing dependency
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loader: Option<String>,
+    /// Whether the node is depended on but is not a dependency of the project
+    pub missing: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    Hard,
+    Soft,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DependencyGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    /// Groups of nodes that depend on each other
+    pub cycles: Vec<Vec<String>>,
+}
+
+/// Prints the dependency graph of the project as a tree, or in the `dot` or `json` format
+pub fn print_tree(root_directory: PathBuf, format: &str) -> anyhow::Result<()> {
+    let project = load_project(root_directory)?;
+    let manifest = project.get_manifest::<DependenciesManifest>().context(
+        "Dependency manifest file was not found, make sure to run `crafty install` first",
+    )?;
+    let ids = project
+        .project_details
+        .dependencies
+        .keys()
+        .cloned()
+        .collect();
+    let graph = DependencyGraph::new(ids, &manifest);
+
+    match format {
+        "text" => print!("{}", graph.to_tree()),
+        "dot" => print!("{}", graph.to_dot()),
+        "json" => println!("{}", serde_json::to_string_pretty(&graph)?),
+        format => return Err(anyhow!("Unknown format \"{}\"", format)),
+    }
+
+    Ok(())
+}
+
+impl DependencyGraph {
+    /// Builds the graph of the given dependencies, they are linked by the names and `provides`
+    /// of their descriptors
+    pub fn new(mut ids: Vec<String>, manifest: &DependenciesManifest) -> Self {
+        ids.sort();
+        // Bukkit replaces spaces in plugin names with underscores
+        let normalize = |name: &str| name.replace(' ', "_");
+
+        let mut nodes = BTreeMap::new();
+        let mut names = HashMap::new();
+        for id in &ids {
+            let details = manifest.0.get(id);
+            let descriptor = details.and_then(|details| details.descriptor.as_ref());
+            if let Some(descriptor) = descriptor {
+                for name in std::iter::once(&descriptor.name).chain(&descriptor.provides) {
+                    names.entry(normalize(name)).or_insert_with(|| id.clone());
+                }
+            }
+
+            nodes.insert(
+                id.clone(),
+                Node {
+                    id: id.clone(),
+                    name: descriptor.map(|descriptor| descriptor.name.clone()),
+                    version: descriptor
+                        .and_then(|descriptor| descriptor.version.clone())
+                        .or_else(|| details.and_then(|details| details.version.clone())),
+                    loader: descriptor.map(|descriptor| descriptor.loader.clone()),
+                    missing: false,
+                },
+            );
+        }
+
+        let mut edges = vec![];
+        for id in &ids {
+            let Some(descriptor) = manifest
+                .0
+                .get(id)
+                .and_then(|details| details.descriptor.as_ref())
+            else {
+                continue;
+            };
+
+            let dependencies = descriptor
+                .depend
+                .iter()
+                .map(|name| (name, EdgeKind::Hard))
+                .chain(
+                    descriptor
+                        .softdepend
+                        .iter()
+                        .map(|name| (name, EdgeKind::Soft)),
+                );
+            for (name, kind) in dependencies {
+                let to = names.get(&normalize(name)).cloned().unwrap_or_else(|| {
+                    nodes.entry(name.clone()).or_insert_with(|| Node {
+                        id: name.clone(),
+                        name: None,
+                        version: None,
+                        loader: None,
+                        missing: true,
+                    });
+                    name.clone()
+                });
+                edges.push(Edge {
+                    from: id.clone(),
+                    to,
+                    kind,
+                });
+            }
+        }
+
+        let mut graph = Self {
+            nodes: nodes.into_values().collect(),
+            edges,
+            cycles: vec![],
+        };
+        graph.cycles = graph.find_cycles();
+        graph
+    }
+
+    fn dependencies_of<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Edge> {
+        self.edges.iter().filter(move |edge| edge.from == id)
+    }
+
+    fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Finds the strongly connected components with more than one node (or a node depending on itself)
+    fn find_cycles(&self) -> Vec<Vec<String>> {
+        struct State<'a> {
+            graph: &'a DependencyGraph,
+            index: usize,
+            indexes: HashMap<&'a str, usize>,
+            low_links: HashMap<&'a str, usize>,
+            stack: Vec<&'a str>,
+            cycles: Vec<Vec<String>>,
+        }
+
+        // Tarjan's algorithm
+        fn visit<'a>(state: &mut State<'a>, id: &'a str) {
+            state.indexes.insert(id, state.index);
+            state.low_links.insert(id, state.index);
+            state.index += 1;
+            state.stack.push(id);
+
+            for edge in state.graph.dependencies_of(id) {
+                let to = edge.to.as_str();
+                if !state.indexes.contains_key(to) {
+                    visit(state, to);
+                    let low_link = state.low_links[id].min(state.low_links[to]);
+                    state.low_links.insert(id, low_link);
+                } else if state.stack.contains(&to) {
+                    let low_link = state.low_links[id].min(state.indexes[to]);
+                    state.low_links.insert(id, low_link);
+                }
+            }
+
+            if state.low_links[id] == state.indexes[id] {
+                let position = state.stack.iter().rposition(|other| *other == id).unwrap();
+                let mut component: Vec<String> =
+                    state.stack.drain(position..).map(String::from).collect();
+
+                let is_self_dependent = state.graph.dependencies_of(id).any(|edge| edge.to == id);
+                if component.len() > 1 || is_self_dependent {
+                    component.sort();
+                    state.cycles.push(component);
+                }
+            }
+        }
+
+        let mut state = State {
+            graph: self,
+            index: 0,
+            indexes: HashMap::new(),
+            low_links: HashMap::new(),
+            stack: vec![],
+            cycles: vec![],
+        };
+        for node in &self.nodes {
+            if !state.indexes.contains_key(node.id.as_str()) {
+                visit(&mut state, &node.id);
+            }
+        }
+
+        state.cycles.sort();
+        state.cycles
+    }
+
+    fn is_in_cycle(&self, edge: &Edge) -> bool {
+        self.cycles
+            .iter()
+            .any(|cycle| cycle.contains(&edge.from) && cycle.contains(&edge.to))
+    }
+
+    fn label(node: &Node) -> String {
+        match (&node.name, &node.version) {
+            (Some(name), Some(version)) if !name.eq_ignore_ascii_case(&node.id) => {
+                format!("{} ({} {})", node.id, name, version)
+            }
+            (_, Some(version)) => format!("{} {}", node.id, version),
+            _ => node.id.clone(),
+        }
+    }
+
+    /// Formats the graph as a tree from the dependencies nothing depends on,
+    /// nodes already shown are marked with `(*)`
+    pub fn to_tree(&self) -> String {
+        let depended_on: HashSet<&str> = self.edges.iter().map(|edge| edge.to.as_str()).collect();
+        let mut roots: Vec<&Node> = self
+            .nodes
+            .iter()
+            .filter(|node| !node.missing && !depended_on.contains(node.id.as_str()))
+            .collect();
+        // Nodes that are only part of cycles are not depended on by a root
+        for cycle in &self.cycles {
+            let is_reachable = cycle.iter().any(|id| {
+                self.edges
+                    .iter()
+                    .any(|edge| edge.to == *id && !cycle.contains(&edge.from))
+            });
+            if !is_reachable {
+                roots.extend(self.node(&cycle[0]));
+            }
+        }
+        roots.sort_by(|first, second| first.id.cmp(&second.id));
+
+        let mut output = String::new();
+        let mut expanded = HashSet::new();
+        for root in roots {
+            output.push_str(&Self::label(root));
+            output.push('\n');
+            expanded.insert(root.id.clone());
+            self.write_children(
+                &root.id,
+                "",
+                &mut vec![root.id.clone()],
+                &mut expanded,
+                &mut output,
+            );
+        }
+
+        output
+    }
+
+    fn write_children(
+        &self,
+        id: &str,
+        prefix: &str,
+        path: &mut Vec<String>,
+        expanded: &mut HashSet<String>,
+        output: &mut String,
+    ) {
+        let edges: Vec<&Edge> = self.dependencies_of(id).collect();
+        for (index, edge) in edges.iter().enumerate() {
+            let is_last = index == edges.len() - 1;
+            let Some(node) = self.node(&edge.to) else {
+                continue;
+            };
+
+            let mut notes = vec![];
+            if edge.kind == EdgeKind::Soft {
+                notes.push("soft");
+            }
+            if node.missing {
+                notes.push(match edge.kind {
+                    EdgeKind::Hard => "missing",
+                    EdgeKind::Soft => "not installed",
+                });
+            }
+            let is_cycle = path.contains(&node.id);
+            if is_cycle {
+                notes.push("cycle");
+            }
+
+            let mut line = format!(
+                "{}{}{}",
+                prefix,
+                if is_last { "└── " } else { "├── " },
+                Self::label(node)
+            );
+            if !notes.is_empty() {
+                line.push_str(&format!(" ({})", notes.join(", ")));
+            }
+
+            let has_children = self.dependencies_of(&node.id).next().is_some();
+            let is_expanded = !expanded.insert(node.id.clone());
+            if has_children && is_expanded && !is_cycle {
+                line.push_str(" (*)");
+            }
+            output.push_str(&line);
+            output.push('\n');
+
+            if has_children && !is_expanded && !is_cycle {
+                let prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+                path.push(node.id.clone());
+                self.write_children(&node.id, &prefix, path, expanded, output);
+                path.pop();
+            }
+        }
+    }
+
+    /// Formats the graph in the Graphviz `dot` format, soft dependencies are dashed,
+    /// missing nodes are red and cycles are orange
+    pub fn to_dot(&self) -> String {
+        let quote = |value: &str| serde_json::to_string(value).unwrap();
+        let mut output = String::from("digraph dependencies {\n");
+
+        for node in &self.nodes {
+            let label = match &node.version {
+                Some(version) => format!("{}\n{}", node.id, version),
+                None => node.id.clone(),
+            };
+            let style = if node.missing {
+                ", style=dashed, color=red, fontcolor=red"
+            } else {
+                ""
+            };
+            output.push_str(&format!(
+                "    {} [label={}{}];\n",
+                quote(&node.id),
+                quote(&label),
+                style
+            ));
+        }
+
+        for edge in &self.edges {
+            let mut attributes = vec![];
+            if edge.kind == EdgeKind::Soft {
+                attributes.push("style=dashed");
+            }
+            if self.is_in_cycle(edge) {
+                attributes.push("color=orange");
+            }
+            let attributes = if attributes.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", attributes.join(", "))
+            };
+            output.push_str(&format!(
+                "    {} -> {}{};\n",
+                quote(&edge.from),
+                quote(&edge.to),
+                attributes
+            ));
+        }
+
+        output.push_str("}\n");
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependencies::DependencyType;
+    use crate::descriptor::Descriptor;
+    use crate::manifests::DependencyDetails;
+
+    fn plugin(
+        name: &str,
+        version: &str,
+        depend: &[&str],
+        softdepend: &[&str],
+    ) -> DependencyDetails {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        DependencyDetails {
+            dependency_type: DependencyType::Plugin,
+            target: None,
+            version: None,
+            archive: None,
+            files: vec![],
+            descriptor: Some(Descriptor {
+                name: name.into(),
+                version: Some(version.into()),
+                depend: names(depend),
+                softdepend: names(softdepend),
+                loader: "bukkit".into(),
+                ..Default::default()
+            }),
+            profiles: vec![],
+        }
+    }
+
+    fn graph(plugins: Vec<(&str, DependencyDetails)>) -> DependencyGraph {
+        let ids = plugins.iter().map(|(id, _)| id.to_string()).collect();
+        let manifest = DependenciesManifest::new(
+            plugins
+                .into_iter()
+                .map(|(id, details)| (id.to_string(), details))
+                .collect(),
+        );
+        DependencyGraph::new(ids, &manifest)
+    }
+
+    fn example() -> DependencyGraph {
+        graph(vec![
+            (
+                "alpha",
+                plugin("alpha", "1.0", &["Vault", "Beta Plugin"], &["Gamma"]),
+            ),
+            ("beta", plugin("Beta_Plugin", "2.0", &[], &["alpha"])),
+            ("vault", plugin("Vault", "1.7", &[], &[])),
+        ])
+    }
+
+    #[test]
+    fn finds_cycles() {
+        assert_eq!(example().cycles, vec![vec!["alpha", "beta"]]);
+
+        let graph = graph(vec![
+            ("a", plugin("a", "1", &["b"], &[])),
+            ("b", plugin("b", "1", &["c"], &[])),
+            ("c", plugin("c", "1", &["a"], &[])),
+            ("d", plugin("d", "1", &["d"], &[])),
+            ("e", plugin("e", "1", &["a"], &[])),
+        ]);
+        assert_eq!(graph.cycles, vec![vec!["a", "b", "c"], vec!["d"]]);
+    }
+
+    #[test]
+    fn links_dependencies_by_name() {
+        let graph = example();
+
+        let missing: Vec<&str> = graph
+            .nodes
+            .iter()
+            .filter(|node| node.missing)
+            .map(|node| node.id.as_str())
+            .collect();
+        assert_eq!(missing, vec!["Gamma"]);
+        assert!(graph
+            .edges
+            .iter()
+            .any(|edge| edge.from == "alpha" && edge.to == "beta" && edge.kind == EdgeKind::Hard));
+    }
+
+    #[test]
+    fn formats_trees() {
+        assert_eq!(
+            example().to_tree(),
+            "alpha 1.0
+├── vault 1.7
+├── beta (Beta_Plugin 2.0)
+│   └── alpha 1.0 (soft, cycle)
+└── Gamma (soft, not installed)
+"
+        );
+    }
+
+    #[test]
+    fn marks_cycles_and_missing_nodes_in_dot() {
+        let dot = example().to_dot();
+
+        assert!(dot.contains("\"alpha\" -> \"beta\" [color=orange];"));
+        assert!(dot.contains("\"beta\" -> \"alpha\" [style=dashed, color=orange];"));
+        assert!(
+            dot.contains("\"Gamma\" [label=\"Gamma\", style=dashed, color=red, fontcolor=red];")
+        );
+    }
+}
@@ -1,53 +1,714 @@
+use crate::sources::maven;
+use crate::{auth, cache, InstallOptions, ProjectMetadata};
 use anyhow::{anyhow, Context};
 use common::utils;
 use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::fs::OpenOptions;
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-pub(crate) async fn download_server(
-    source: &str,
-    target_directory: PathBuf,
-) -> anyhow::Result<PathBuf> {
-    fs::create_dir_all(&target_directory)?;
+use glob::Pattern;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use reqwest::header::{HeaderMap, ETAG, IF_RANGE, LAST_MODIFIED, LOCATION, RANGE};
+use reqwest::{StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
 
+/// Runs an installer JAR with the given arguments, failing if the installer does not exit successfully
+pub(crate) async fn run_installer(
+    installer_jar: &Path,
+    args: &[String],
+    working_directory: &Path,
+) -> anyhow::Result<()> {
+    let output = Command::new(crate::java_binary())
+        .current_dir(working_directory)
+        .arg("-jar")
+        .arg(installer_jar)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("Could not run installer, make sure java is installed")?;
+
+    log::debug!("{}", String::from_utf8_lossy(&output.stdout));
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "The installer \"{}\" failed ({}):\n{}",
+            installer_jar.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(())
+}
+
+const USER_AGENT: &str = concat!("zeke-io/crafty/", env!("CARGO_PKG_VERSION"));
+
+/// Maximum number of redirects followed by a request
+const MAX_REDIRECTS: usize = 10;
+
+/// HTTP client used for every request, some APIs (eg. Modrinth) require a user agent.
+/// Redirects to another origin are not followed, see `Downloader::send`
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            let is_same_origin = attempt
+                .previous()
+                .last()
+                .is_some_and(|previous| previous.origin() == attempt.url().origin());
+
+            if attempt.previous().len() > MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if is_same_origin {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        }))
+        .build()
+        .expect("Could not create HTTP client")
+}
+
+/// Makes the network requests of a command, each command creates its own
+/// so the settings and credentials of a project are never used by another one
+#[derive(Clone)]
+pub(crate) struct Downloader {
+    /// Shared by every request, so connections are reused
+    client: reqwest::Client,
+    /// Whether network requests are disabled, files can only be installed from the cache
+    offline: bool,
+    /// Whether files are downloaded again instead of being taken from the cache
+    refresh: bool,
+    /// Directory of the user cache, files are not cached if it could not be found
+    cache_directory: Option<PathBuf>,
+    /// Extra headers sent to every URL under a prefix (eg. repository credentials)
+    headers: Vec<(Url, HeaderMap)>,
+    /// Extra headers sent to every URL whose host (or `host:port`) matches a pattern
+    host_headers: Vec<(Pattern, HeaderMap)>,
+}
+
+/// Whether a URL has the same origin (scheme, host and port) as a prefix
+/// and its path starts with the whole segments of the prefix path
+pub(crate) fn is_under_prefix(url: &Url, prefix: &Url) -> bool {
+    let prefix_path = prefix.path().trim_end_matches('/');
+    url.origin() == prefix.origin()
+        && (url.path() == prefix_path || url.path().starts_with(&format!("{}/", prefix_path)))
+}
+
+/// Local sources are relative to the project directory (not the current one), URLs are returned as is
+pub(crate) fn local_source(root_directory: &Path, source: &str) -> String {
     if utils::is_url(source) {
-        log::info!(
-            "Downloading server JAR file \"{}\"...",
-            utils::get_filename_from_url(source)
-        );
+        return source.to_string();
+    }
+
+    root_directory.join(source).to_string_lossy().to_string()
+}
+
+/// Number of times a failed download is retried before giving up
+const DOWNLOAD_RETRIES: u32 = 3;
+
+enum DownloadError {
+    /// The server rejected the request, retrying would not help
+    Fatal(anyhow::Error),
+    Retry(anyhow::Error),
+}
+
+impl Downloader {
+    pub(crate) fn new(offline: bool) -> Self {
+        Self {
+            client: http_client(),
+            offline,
+            refresh: false,
+            cache_directory: cache::cache_directory().ok(),
+            headers: vec![],
+            host_headers: vec![],
+        }
+    }
+
+    /// Creates a downloader with the credentials of a project,
+    /// for the hosts of the `download` section and the Maven repositories
+    pub(crate) fn for_project(
+        project: &ProjectMetadata,
+        options: &InstallOptions,
+    ) -> anyhow::Result<Self> {
+        let mut downloader = Self::new(options.offline);
+        downloader.refresh = options.force;
+        auth::register(&mut downloader, &project.download)?;
+        maven::register_credentials(&mut downloader, &project.repositories)?;
+        Ok(downloader)
+    }
+
+    pub(crate) fn check_online(&self, url: &str) -> anyhow::Result<()> {
+        if self.offline {
+            return Err(anyhow!(
+                "Could not fetch \"{}\", network requests are disabled with `--offline`",
+                url
+            ));
+        }
 
-        Ok(download_file(source.into(), target_directory).await?)
-    } else {
-        log::info!("Installing server JAR from \"{}\"...", source);
+        Ok(())
+    }
+
+    /// Registers headers to send with every request to URLs under the given prefix
+    pub(crate) fn register_headers(
+        &mut self,
+        url_prefix: &str,
+        headers: HeaderMap,
+    ) -> anyhow::Result<()> {
+        let url_prefix =
+            Url::parse(url_prefix).with_context(|| format!("Invalid URL \"{}\"", url_prefix))?;
+        self.headers.retain(|(prefix, _)| *prefix != url_prefix);
+        self.headers.push((url_prefix, headers));
+        Ok(())
+    }
+
+    /// Registers headers to send with every request to hosts matching the given pattern (eg. `*.example.com`)
+    pub(crate) fn register_host_headers(&mut self, host_pattern: Pattern, headers: HeaderMap) {
+        self.host_headers
+            .retain(|(pattern, _)| *pattern != host_pattern);
+        self.host_headers.push((host_pattern, headers));
+    }
+
+    /// Creates a GET request with the headers required by the source of the URL
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .get(url)
+            .headers(crate::sources::request_headers(url));
+
+        let Ok(parsed_url) = Url::parse(url) else {
+            return request;
+        };
+
+        for (prefix, headers) in &self.headers {
+            if is_under_prefix(&parsed_url, prefix) {
+                request = request.headers(headers.clone());
+            }
+        }
+
+        let host = parsed_url.host_str().unwrap_or_default();
+        let host_with_port = parsed_url.port().map(|port| format!("{}:{}", host, port));
+        for (pattern, headers) in &self.host_headers {
+            let is_matching = pattern.matches(host)
+                || host_with_port
+                    .as_deref()
+                    .is_some_and(|host_with_port| pattern.matches(host_with_port));
+            if is_matching {
+                request = request.headers(headers.clone());
+            }
+        }
+
+        request
+    }
+
+    /// Sends a GET request, redirects to another origin are followed with a new request
+    /// so the headers registered for a host (eg. an API key) are never sent to another one
+    async fn send(
+        &self,
+        url: &str,
+        configure: impl Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+    ) -> anyhow::Result<reqwest::Response> {
+        let mut url = url.to_string();
+
+        for _ in 0..=MAX_REDIRECTS {
+            let response = configure(self.get(&url)).send().await?;
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok());
 
-        let source_path = PathBuf::from(source);
-        let file_name = Path::new(&source_path)
+            match location {
+                Some(location) if response.status().is_redirection() => {
+                    url = response.url().join(location)?.to_string();
+                }
+                _ => return Ok(response),
+            }
+        }
+
+        Err(anyhow!("Too many redirects from \"{}\"", url))
+    }
+
+    pub(crate) async fn download_server(
+        &self,
+        source: &str,
+        file_name: Option<&str>,
+        sha256: Option<&str>,
+        target_directory: PathBuf,
+    ) -> anyhow::Result<PathBuf> {
+        fs::create_dir_all(&target_directory)?;
+
+        if utils::is_url(source) {
+            let file_name = file_name
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| utils::get_filename_from_url(source));
+            log::info!("Downloading server JAR file \"{}\"...", file_name);
+
+            Ok(self
+                .download_file(source.into(), target_directory.join(file_name), sha256)
+                .await?)
+        } else {
+            log::info!("Installing server JAR from \"{}\"...", source);
+
+            let source_path = PathBuf::from(source);
+            let file_name = Path::new(&source_path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("server.jar");
+            let dest_path = Path::new(&target_directory).join(file_name);
+
+            fs::copy(source_path, &dest_path).context("Could not copy server JAR file")?;
+            Ok(dest_path)
+        }
+    }
+
+    /// Downloads a file to a temporary `.part` file and renames it once complete,
+    /// an existing partial download is resumed and failed downloads are retried with backoff.
+    /// Files are taken from the user cache by their checksum if it is known, otherwise by their URL
+    pub(crate) async fn download_file(
+        &self,
+        url: String,
+        mut destination: PathBuf,
+        sha256: Option<&str>,
+    ) -> anyhow::Result<PathBuf> {
+        if destination.is_dir() {
+            let filename: String = utils::get_filename_from_url(&url);
+            destination = destination.join(filename);
+        }
+
+        // Offline installs can only use the cache, even with `--force`
+        if !self.refresh || self.offline {
+            if let Some(blob) = self.cached_file(&url, sha256) {
+                if cache::restore(&blob, &destination)? {
+                    log::debug!("Using cached file for \"{}\"", url);
+                    return Ok(destination);
+                }
+                log::warn!(
+                    "The cached file of \"{}\" was modified, downloading it again...",
+                    url
+                );
+            }
+        }
+        self.check_online(&url)?;
+
+        // Partial files are named after the URL, so different files with the same name are never mixed
+        let url_hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+        let mut part_file = destination.clone().into_os_string();
+        part_file.push(format!(".{}.part", &url_hash[..16]));
+        let part_file = PathBuf::from(part_file);
+
+        let file_name = destination
             .file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or("server.jar");
-        let dest_path = Path::new(&target_directory).join(file_name);
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let progress = progress_bar(&file_name);
+
+        let mut attempt = 0;
+        loop {
+            match self
+                .download_part(&url, &part_file, progress.as_ref())
+                .await
+            {
+                Ok(()) => break,
+                Err(DownloadError::Retry(err)) if attempt < DOWNLOAD_RETRIES => {
+                    attempt += 1;
+                    log::warn!("{} Retrying ({}/{})...", err, attempt, DOWNLOAD_RETRIES);
+                    tokio::time::sleep(Duration::from_millis(500 * 2u64.pow(attempt))).await;
+                }
+                Err(DownloadError::Retry(err) | DownloadError::Fatal(err)) => {
+                    if let Some(progress) = progress {
+                        progress.abandon();
+                    }
+                    return Err(err);
+                }
+            }
+        }
 
-        fs::copy(source_path, &dest_path).context("Could not copy server JAR file")?;
-        Ok(dest_path)
+        if let Some(progress) = progress {
+            progress.finish_and_clear();
+        }
+        fs::rename(&part_file, &destination)
+            .with_context(|| format!("Could not save \"{}\"", destination.display()))?;
+        fs::remove_file(PartialDownload::path(&part_file)).ok();
+
+        if let Some(cache_directory) = &self.cache_directory {
+            if let Err(err) = cache::store(cache_directory, &destination, &url) {
+                log::warn!("Could not cache \"{}\" ({})", url, err);
+            }
+        }
+        Ok(destination)
+    }
+
+    /// Returns the cached file with the given checksum, or the last file downloaded from the URL
+    fn cached_file(&self, url: &str, sha256: Option<&str>) -> Option<PathBuf> {
+        cache::find(self.cache_directory.as_deref()?, url, sha256)
+    }
+
+    /// Downloads the rest of a partial file, or all of it if the server does not support ranges
+    /// or the remote file changed
+    async fn download_part(
+        &self,
+        url: &str,
+        part_file: &Path,
+        progress: Option<&ProgressBar>,
+    ) -> Result<(), DownloadError> {
+        // Partial files are only resumed if the server can tell whether the remote file changed
+        let partial = PartialDownload::load(part_file).filter(|partial| partial.url == url);
+        let if_range = partial
+            .as_ref()
+            .and_then(|partial| partial.if_range())
+            .filter(|_| part_file.exists());
+        let downloaded = match if_range {
+            Some(_) => fs::metadata(part_file).map(|meta| meta.len()).unwrap_or(0),
+            None => 0,
+        };
+
+        let mut response = self
+            .send(url, |request| match if_range {
+                Some(if_range) if downloaded > 0 => request
+                    .header(RANGE, format!("bytes={}-", downloaded))
+                    .header(IF_RANGE, if_range),
+                _ => request,
+            })
+            .await
+            .map_err(|err| {
+                DownloadError::Retry(anyhow!(
+                    "Could not download file from \"{}\" ({}).",
+                    url,
+                    err
+                ))
+            })?;
+        let status = response.status();
+
+        if status == StatusCode::RANGE_NOT_SATISFIABLE {
+            // The partial file is invalid (eg. the remote file changed), start over
+            fs::remove_file(part_file).map_err(|err| DownloadError::Fatal(err.into()))?;
+            fs::remove_file(PartialDownload::path(part_file)).ok();
+            return Err(DownloadError::Retry(anyhow!(
+                "Could not resume download from \"{}\".",
+                url
+            )));
+        } else if !status.is_success() {
+            let err = anyhow!(
+                "Could not download file from \"{}\" (status {}).",
+                url,
+                status
+            );
+            return Err(
+                if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                    DownloadError::Retry(err)
+                } else {
+                    DownloadError::Fatal(err)
+                },
+            );
+        }
+
+        // The whole file is sent if it changed since the partial download
+        let resumed = status == StatusCode::PARTIAL_CONTENT && downloaded > 0;
+        if !resumed {
+            let partial = PartialDownload::new(url, &response);
+            fs::write(
+                PartialDownload::path(part_file),
+                serde_json::to_string(&partial).unwrap(),
+            )
+            .map_err(|err| DownloadError::Fatal(err.into()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed)
+            .truncate(!resumed)
+            .open(part_file)
+            .map_err(|err| DownloadError::Fatal(err.into()))?;
+
+        let offset = if resumed { downloaded } else { 0 };
+        if let Some(progress) = progress {
+            progress.set_length(offset + response.content_length().unwrap_or_default());
+            progress.set_position(offset);
+        }
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|err| DownloadError::Retry(err.into()))?
+        {
+            file.write_all(&chunk)
+                .map_err(|err| DownloadError::Fatal(err.into()))?;
+            if let Some(progress) = progress {
+                progress.inc(chunk.len() as u64);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn fetch_json<T: DeserializeOwned>(&self, url: &str) -> anyhow::Result<T> {
+        self.check_online(url)?;
+        let response = self.send(url, |request| request).await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Could not fetch \"{}\" (status {}).",
+                url,
+                response.status()
+            ));
+        }
+
+        response
+            .json::<T>()
+            .await
+            .with_context(|| format!("Could not parse response from \"{}\"", url))
+    }
+
+    pub(crate) async fn fetch_text(&self, url: &str) -> anyhow::Result<String> {
+        self.fetch_optional_text(url)
+            .await?
+            .ok_or_else(|| anyhow!("Could not fetch \"{}\" (status 404 Not Found).", url))
+    }
+
+    /// Fetches a text file, `None` if it does not exist
+    pub(crate) async fn fetch_optional_text(&self, url: &str) -> anyhow::Result<Option<String>> {
+        self.check_online(url)?;
+        let response = self.send(url, |request| request).await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        } else if !response.status().is_success() {
+            return Err(anyhow!(
+                "Could not fetch \"{}\" (status {}).",
+                url,
+                response.status()
+            ));
+        }
+
+        Ok(Some(response.text().await?))
+    }
+}
+
+/// Validators of a partial download, stored next to the `.part` file so only the same remote file is resumed
+#[derive(Serialize, Deserialize, Debug)]
+struct PartialDownload {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+}
+
+impl PartialDownload {
+    fn path(part_file: &Path) -> PathBuf {
+        let mut path = part_file.as_os_str().to_owned();
+        path.push(".json");
+        PathBuf::from(path)
     }
+
+    fn load(part_file: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(Self::path(part_file)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    fn new(url: &str, response: &reqwest::Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+
+        Self {
+            url: url.into(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    /// Value of the `If-Range` header, weak ETags cannot be used to resume a download
+    fn if_range(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+/// Progress bars are only shown if stdout is a terminal
+fn progress_bar(file_name: &str) -> Option<ProgressBar> {
+    static PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+
+    let progress = PROGRESS
+        .get_or_init(|| MultiProgress::with_draw_target(ProgressDrawTarget::stdout()))
+        .add(ProgressBar::new(0));
+    progress.set_style(
+        ProgressStyle::with_template("{msg:30!} [{bar:30}] {bytes}/{total_bytes} ({eta})")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    progress.set_message(file_name.to_string());
+
+    Some(progress)
 }
 
-pub async fn download_file(url: String, mut destination: PathBuf) -> anyhow::Result<PathBuf> {
-    let response = reqwest::get(&url).await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if !response.status().is_success() {
-        return Err(anyhow!("Could not download file from \"{}\".", &url));
+    #[test]
+    fn matches_urls_under_a_prefix() {
+        let prefix = Url::parse("https://repo.example.com/releases/").unwrap();
+        let is_under = |url: &str| is_under_prefix(&Url::parse(url).unwrap(), &prefix);
+
+        assert!(is_under("https://repo.example.com/releases"));
+        assert!(is_under("https://repo.example.com/releases/a/b.jar"));
+        assert!(!is_under("https://repo.example.com/releases-private/b.jar"));
+        assert!(!is_under(
+            "https://repo.example.com.evil.com/releases/b.jar"
+        ));
+        assert!(!is_under("http://repo.example.com/releases/b.jar"));
+        assert!(!is_under("https://repo.example.com:8443/releases/b.jar"));
+    }
+
+    /// Creates a partial download of the URL, with the validators of the sidecar file if any
+    fn partial_download(directory: &Path, url: &str, etag: Option<&str>) -> PathBuf {
+        let part_file = directory.join("file.jar.part");
+        fs::write(&part_file, "abc").unwrap();
+        fs::remove_file(PartialDownload::path(&part_file)).ok();
+        if let Some(etag) = etag {
+            let partial = PartialDownload {
+                url: url.into(),
+                etag: Some(etag.into()),
+                last_modified: None,
+            };
+            fs::write(
+                PartialDownload::path(&part_file),
+                serde_json::to_string(&partial).unwrap(),
+            )
+            .unwrap();
+        }
+        part_file
     }
 
-    if destination.is_dir() {
-        let filename: String = utils::get_filename_from_url(&url);
-        destination = destination.join(filename);
+    #[tokio::test]
+    async fn resumes_partial_downloads_of_the_same_file() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/file.jar")
+            .match_header("range", "bytes=3-")
+            .match_header("if-range", "\"v1\"")
+            .with_status(206)
+            .with_header("etag", "\"v1\"")
+            .with_body("def")
+            .create_async()
+            .await;
+        let url = format!("{}/file.jar", server.url());
+        let directory = tempfile::tempdir().unwrap();
+
+        let part_file = partial_download(directory.path(), &url, Some("\"v1\""));
+        assert!(Downloader::new(false)
+            .download_part(&url, &part_file, None)
+            .await
+            .is_ok());
+        assert_eq!(fs::read_to_string(&part_file).unwrap(), "abcdef");
     }
 
-    let mut file = File::create(&destination)?;
-    let content = response.bytes().await?;
+    #[tokio::test]
+    async fn restarts_partial_downloads_that_cannot_be_validated() {
+        let mut server = mockito::Server::new_async().await;
+        let changed = server
+            .mock("GET", "/file.jar")
+            .match_header("if-range", "\"v1\"")
+            .with_header("etag", "\"v2\"")
+            .with_body("abcdef")
+            .create_async()
+            .await;
+        let restarted = server
+            .mock("GET", "/file.jar")
+            .match_header("range", mockito::Matcher::Missing)
+            .with_body("abcdef")
+            .expect(2)
+            .create_async()
+            .await;
+        let url = format!("{}/file.jar", server.url());
+        let directory = tempfile::tempdir().unwrap();
+
+        // The remote file changed, the server ignores the range and sends all of it
+        let part_file = partial_download(directory.path(), &url, Some("\"v1\""));
+        assert!(Downloader::new(false)
+            .download_part(&url, &part_file, None)
+            .await
+            .is_ok());
+        assert_eq!(fs::read_to_string(&part_file).unwrap(), "abcdef");
+        let partial = PartialDownload::load(&part_file).unwrap();
+        assert_eq!(partial.etag.as_deref(), Some("\"v2\""));
+
+        // Partial files without validators, or of another URL, are downloaded again
+        let part_file = partial_download(directory.path(), &url, None);
+        assert!(Downloader::new(false)
+            .download_part(&url, &part_file, None)
+            .await
+            .is_ok());
+        assert_eq!(fs::read_to_string(&part_file).unwrap(), "abcdef");
+
+        let other_url = format!("{}/other.jar", server.url());
+        let part_file = partial_download(directory.path(), &other_url, Some("\"v1\""));
+        assert!(Downloader::new(false)
+            .download_part(&url, &part_file, None)
+            .await
+            .is_ok());
+        assert_eq!(fs::read_to_string(&part_file).unwrap(), "abcdef");
 
-    file.write_all(&content)?;
-    Ok(destination)
+        changed.assert_async().await;
+        restarted.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn downloads_files_from_the_cache_by_url() {
+        let mut server = mockito::Server::new_async().await;
+        let download = server
+            .mock("GET", "/file.jar")
+            .with_body("abc")
+            .expect(2)
+            .create_async()
+            .await;
+        let url = format!("{}/file.jar", server.url());
+        let directory = tempfile::tempdir().unwrap();
+        let downloader = Downloader {
+            cache_directory: Some(directory.path().join("cache")),
+            ..Downloader::new(false)
+        };
+
+        let first = directory.path().join("first.jar");
+        let second = directory.path().join("second.jar");
+        downloader
+            .download_file(url.clone(), first.clone(), None)
+            .await
+            .unwrap();
+        downloader
+            .download_file(url.clone(), second.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&second).unwrap(), "abc");
+
+        // Modified cached files are downloaded again
+        fs::remove_file(&second).unwrap();
+        fs::write(&first, "modified").unwrap();
+        downloader
+            .download_file(url.clone(), second.clone(), None)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&second).unwrap(), "abc");
+
+        download.assert_async().await;
+    }
 }
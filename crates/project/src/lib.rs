@@ -1,26 +1,57 @@
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 use std::{env, fs};
 
-use crate::dependencies::Dependency;
-use crate::manifests::{Manifest, VersionManifest};
+use crate::auth::HostAuth;
+use crate::dependencies::{Dependency, Repository};
+use crate::installer::Downloader;
+use crate::lockfile::{LockedServer, Lockfile};
+use crate::manifests::{DependenciesManifest, Manifest};
 use crate::settings::ProjectSettings;
 use anyhow::{anyhow, Context};
 use common::utils;
 use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
 
+mod archive;
+pub mod auth;
+pub mod cache;
+mod checksum;
+mod datapack;
 pub mod dependencies;
+pub mod descriptor;
+mod editor;
+pub mod graph;
 mod installer;
+mod lockfile;
 pub mod manifests;
 pub mod packager;
+mod providers;
 pub mod settings;
+mod sources;
+pub mod updates;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
 pub struct Server {
-    pub source: String,
-    pub brand: String,
-    pub version: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub brand: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Build to use when resolving the server from its brand, defaults to the latest build
+    #[serde(default)]
+    pub build: Option<u32>,
+    /// Mod loader version for `fabric` and `quilt` servers, defaults to the latest stable loader
+    #[serde(default)]
+    pub loader_version: Option<String>,
+    /// Expected checksums of the server JAR, the install fails if they do not match
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub sha512: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -30,6 +61,12 @@ pub struct ProjectMetadata {
     pub server: Server,
     #[serde(default)]
     pub dependencies: HashMap<String, Dependency>,
+    /// Maven repositories used to resolve `maven:` dependencies
+    #[serde(default)]
+    pub repositories: Vec<Repository>,
+    /// Headers or credentials sent when downloading from the hosts matching each pattern
+    #[serde(default)]
+    pub download: HashMap<String, HostAuth>,
 }
 
 pub struct Project {
@@ -71,30 +108,299 @@ pub fn load_project<P: AsRef<Path>>(path: P) -> anyhow::Result<Project> {
     Ok(project)
 }
 
-pub async fn install(root_directory: PathBuf, _force: bool) -> anyhow::Result<()> {
+/// Default number of dependencies downloaded at the same time
+pub const DEFAULT_JOBS: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    /// Download everything again, even files that are already installed
+    pub force: bool,
+    /// Entries to update in the lockfile (dependency ids or `server`), an empty list updates all of them
+    pub update: Option<Vec<String>>,
+    /// Fail if the lockfile is missing or out of date, instead of updating it
+    pub locked: bool,
+    /// Maximum number of dependencies downloaded at the same time
+    pub jobs: usize,
+    /// Fail instead of making network requests when a file is not in the cache
+    pub offline: bool,
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            force: false,
+            update: None,
+            locked: false,
+            jobs: DEFAULT_JOBS,
+            offline: false,
+        }
+    }
+}
+
+impl InstallOptions {
+    pub(crate) fn should_update(&self, id: &str) -> bool {
+        match &self.update {
+            Some(ids) => ids.is_empty() || ids.iter().any(|update_id| update_id == id),
+            None => false,
+        }
+    }
+}
+
+pub async fn install(root_directory: PathBuf, options: InstallOptions) -> anyhow::Result<()> {
     let project = load_project(root_directory)?;
-    let server = &project.project_details.server;
+    let downloader = Downloader::for_project(&project.project_details, &options)?;
+    install_project(&project, &options, &downloader).await
+}
 
-    let server_jar_path = installer::download_server(
-        &server.source,
-        project.root_directory.join(".crafty").join("versions"),
-    )
-    .await?;
+/// Installs the server and the dependencies of a project, updating its lockfile
+pub(crate) async fn install_project(
+    project: &Project,
+    options: &InstallOptions,
+    downloader: &Downloader,
+) -> anyhow::Result<()> {
+    let details = &project.project_details;
+    let server = &details.server;
+
+    let lockfile = Lockfile::load(&project.root_directory)?;
+    if options.locked {
+        let lockfile = lockfile.as_ref().with_context(|| {
+            format!(
+                "The \"{}\" file was not found, run `crafty install` to generate it",
+                lockfile::LOCKFILE_NAME
+            )
+        })?;
+
+        let outdated = lockfile.outdated_entries(details);
+        if !outdated.is_empty() {
+            return Err(anyhow!(
+                "The \"{}\" file is out of date ({}), run `crafty install` to update it",
+                lockfile::LOCKFILE_NAME,
+                outdated.join(", ")
+            ));
+        }
+    }
+    let mut lockfile = lockfile.unwrap_or_default();
 
-    let version_manifest = VersionManifest::new(&server.source, server_jar_path);
+    let locked_server = lockfile
+        .locked_server(server)
+        .filter(|_| !options.should_update("server"));
+    let is_locked = locked_server.is_some();
+    let (version_manifest, resolved_server) = providers::install_server(
+        server,
+        &project.root_directory,
+        locked_server,
+        options,
+        downloader,
+    )
+    .await
+    .with_context(|| {
+        if is_locked {
+            "Could not install the locked server, if it was changed on purpose, run `crafty install --update server`"
+        } else {
+            "Could not install the server"
+        }
+    })?;
+    lockfile.server = Some(LockedServer::new(server, resolved_server));
     version_manifest.save_manifest(&project.root_directory.join(".crafty").join("version.yml"))?;
 
     dependencies::install_dependencies(
-        &project.project_details.dependencies,
+        details,
         &project.root_directory,
+        &mut lockfile,
+        options,
+        downloader,
     )
     .await?;
 
+    if !options.locked {
+        lockfile.save(&project.root_directory)?;
+    }
+
     Ok(())
 }
 
-pub async fn add_dependency(_directory: PathBuf, _dependency_id: String) -> anyhow::Result<()> {
-    todo!()
+/// Path to the java binary, taken from the `JAVA_BIN` or `JAVA_HOME` variables if set
+pub fn java_binary() -> OsString {
+    match (env::var("JAVA_BIN").ok(), env::var("JAVA_HOME").ok()) {
+        (Some(java_bin), _) => PathBuf::from(java_bin).into(),
+        (None, Some(java_home)) => PathBuf::from(java_home).join("bin/java").into(),
+        (None, None) => "java".into(),
+    }
+}
+
+/// Replaces `${VARIABLE}` references with the value of the environment variable,
+/// failing if the variable is not set
+pub(crate) fn interpolate_env(input: &str) -> anyhow::Result<String> {
+    let reg_exp = regex::Regex::new(r"\$\{([A-Za-z_][A-Za-z_0-9]*)}")?;
+    let mut missing = vec![];
+
+    let output = reg_exp.replace_all(input, |caps: &regex::Captures<'_>| {
+        env::var(&caps[1]).unwrap_or_else(|_| {
+            missing.push(caps[1].to_string());
+            "".into()
+        })
+    });
+
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Could not find environment variable(s) {}",
+            missing.join(", ")
+        ));
+    }
+
+    Ok(output.into_owned())
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct AddOptions {
+    /// Dependency id, URL, path or prefixed source (eg. `modrinth:<slug>`)
+    pub target: String,
+    pub version: Option<String>,
+    pub dependency_type: Option<String>,
+    /// Directory to install `custom` dependencies in, relative to the server directory
+    pub target_directory: Option<String>,
+    pub optional: bool,
+}
+
+/// Adds a dependency to `crafty.yml` and installs it
+pub async fn add_dependency(directory: PathBuf, options: AddOptions) -> anyhow::Result<()> {
+    let project = load_project(directory)?;
+    let root_directory = &project.root_directory;
+    let crafty_file = root_directory.join("crafty.yml");
+
+    let (id, source) = dependencies::detect_source(&options.target, root_directory);
+    if project.project_details.dependencies.contains_key(&id) {
+        return Err(anyhow!("The dependency \"{}\" already exists", id));
+    }
+
+    let mut properties = vec![];
+    if let Some(source) = &source {
+        properties.push(("source", source.as_str().into()));
+    }
+    // Dependencies without a source are looked up on Modrinth, which needs a version
+    match (&options.version, &source) {
+        (Some(version), _) => properties.push(("version", version.as_str().into())),
+        (None, None) => properties.push(("version", "latest".into())),
+        (None, Some(_)) => {}
+    }
+    if let Some(dependency_type) = &options.dependency_type {
+        properties.push(("type", dependency_type.as_str().into()));
+    }
+    if let Some(target_directory) = &options.target_directory {
+        properties.push(("target", target_directory.as_str().into()));
+    }
+    // Dependencies are optional by default
+    if !options.optional {
+        properties.push(("required", true.into()));
+    }
+
+    let contents = editor::add_dependency(&fs::read_to_string(&crafty_file)?, &id, &properties)?;
+    let details: ProjectMetadata = serde_yaml::from_str(&contents)
+        .with_context(|| format!("Could not add \"{}\" to \"crafty.yml\"", id))?;
+    let dependency = &details.dependencies[&id];
+    let downloader = Downloader::for_project(&details, &InstallOptions::default())?;
+
+    let mut lockfile = Lockfile::load(root_directory)?.unwrap_or_default();
+    let installed = dependencies::install_dependency(
+        &id,
+        dependency,
+        &details,
+        root_directory,
+        &mut lockfile,
+        &InstallOptions::default(),
+        &downloader,
+    )
+    .await?;
+
+    fs::write(&crafty_file, contents).context("Could not save \"crafty.yml\"")?;
+    log::info!("Added \"{}\" to \"crafty.yml\"", id);
+
+    if let Some(installed) = installed {
+        let mut manifest = load_dependencies_manifest(root_directory)?;
+        manifest.0.insert(id, installed);
+        manifest.save_manifest(&root_directory.join(".crafty").join("dependencies.yml"))?;
+        lockfile.save(root_directory)?;
+
+        // Missing dependencies can be added next, so they do not fail the command
+        let server_version = dependencies::installed_server_version(root_directory, &details);
+        if let Err(err) = descriptor::validate(&manifest.0, server_version.as_deref()) {
+            log::warn!("{}", err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes a dependency from `crafty.yml`, the lockfile and the installed files
+pub fn remove_dependency(directory: PathBuf, id: String) -> anyhow::Result<()> {
+    let project = load_project(directory)?;
+    let root_directory = &project.root_directory;
+    let crafty_file = root_directory.join("crafty.yml");
+
+    if !project.project_details.dependencies.contains_key(&id) {
+        return Err(anyhow!(
+            "The dependency \"{}\" was not found in \"crafty.yml\"",
+            id
+        ));
+    }
+
+    let contents = editor::remove_dependency(&fs::read_to_string(&crafty_file)?, &id)?;
+    serde_yaml::from_str::<ProjectMetadata>(&contents)
+        .with_context(|| format!("Could not remove \"{}\" from \"crafty.yml\"", id))?;
+    fs::write(&crafty_file, contents).context("Could not save \"crafty.yml\"")?;
+
+    let mut manifest = load_dependencies_manifest(root_directory)?;
+    if let Some(removed) = manifest.0.remove(&id) {
+        let dependencies_directory = root_directory.join(".crafty").join("dependencies");
+        for file in removed.files {
+            // Keep files that are shared with other dependencies
+            let is_shared = manifest.0.values().any(|details| {
+                details
+                    .files
+                    .iter()
+                    .any(|other| other.filename == file.filename)
+            });
+            let file_path = dependencies_directory.join(&file.filename);
+            if !is_shared && file_path.exists() {
+                fs::remove_file(&file_path)
+                    .with_context(|| format!("Could not delete \"{}\"", file_path.display()))?;
+            }
+        }
+
+        let extracted_directory = dependencies_directory.join(&id);
+        if removed.archive.is_some() && extracted_directory.is_dir() {
+            fs::remove_dir_all(&extracted_directory).with_context(|| {
+                format!("Could not delete \"{}\"", extracted_directory.display())
+            })?;
+        }
+        manifest.save_manifest(&root_directory.join(".crafty").join("dependencies.yml"))?;
+    }
+
+    let repository_directory = root_directory.join(".crafty").join("src").join(&id);
+    if repository_directory.is_dir() {
+        fs::remove_dir_all(&repository_directory)
+            .with_context(|| format!("Could not delete \"{}\"", repository_directory.display()))?;
+    }
+
+    if let Some(mut lockfile) = Lockfile::load(root_directory)? {
+        lockfile.dependencies.remove(&id);
+        lockfile.save(root_directory)?;
+    }
+
+    log::info!("Removed \"{}\" from \"crafty.yml\"", id);
+    Ok(())
+}
+
+fn load_dependencies_manifest(root_directory: &Path) -> anyhow::Result<DependenciesManifest> {
+    if root_directory
+        .join(".crafty")
+        .join("dependencies.yml")
+        .exists()
+    {
+        DependenciesManifest::load_manifest(root_directory)
+    } else {
+        Ok(DependenciesManifest::new(HashMap::new()))
+    }
 }
 
 pub fn process_files<P: AsRef<Path>>(
@@ -182,3 +488,72 @@ pub fn process_files<P: AsRef<Path>>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = "
+name: test
+server:
+  source: server.jar
+  version: 1.20.4
+dependencies:
+  config:
+    source: files/config.yml
+    required: true
+    type: custom
+    target: config
+";
+
+    #[tokio::test]
+    async fn installs_incrementally_and_checks_the_lockfile() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path();
+        fs::create_dir(root.join("files")).unwrap();
+        fs::write(root.join("server.jar"), "server").unwrap();
+        fs::write(root.join("files/config.yml"), "a: 1").unwrap();
+        // Local sources are relative to the project, not the current directory
+        let project = PROJECT.to_string();
+        fs::write(root.join("crafty.yml"), &project).unwrap();
+
+        // `--locked` needs an existing lockfile
+        let locked = InstallOptions {
+            locked: true,
+            ..Default::default()
+        };
+        assert!(install(root.into(), locked.clone()).await.is_err());
+
+        install(root.into(), InstallOptions::default())
+            .await
+            .unwrap();
+        let installed = root.join(".crafty/dependencies/config.yml");
+        assert_eq!(fs::read_to_string(&installed).unwrap(), "a: 1");
+        assert!(root.join(lockfile::LOCKFILE_NAME).exists());
+
+        // Changed local files and stale files are picked up by the next install
+        fs::write(root.join("files/config.yml"), "a: 2").unwrap();
+        fs::write(root.join(".crafty/dependencies/stale.jar"), "").unwrap();
+        install(root.into(), locked.clone()).await.unwrap();
+        assert_eq!(fs::read_to_string(&installed).unwrap(), "a: 2");
+        assert!(!root.join(".crafty/dependencies/stale.jar").exists());
+
+        // Dependencies that are not in the lockfile fail `--locked` installs
+        let changed = project.replace("config.yml\n", "config.yml\n    version: 2\n");
+        fs::write(root.join("crafty.yml"), changed).unwrap();
+        let err = install(root.into(), locked).await.unwrap_err();
+        assert!(err.to_string().contains("out of date"), "{}", err);
+
+        // Checksums pinned after the install are verified even if the server is up to date
+        let pinned = project.replace("version: 1.20.4\n", "version: 1.20.4\n  sha256: \"00\"\n");
+        fs::write(root.join("crafty.yml"), pinned).unwrap();
+        let err = install(root.into(), InstallOptions::default())
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("Checksum mismatch"),
+            "{:#}",
+            err
+        );
+    }
+}
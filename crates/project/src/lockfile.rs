@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use common::utils;
+use serde::{Deserialize, Serialize};
+
+use crate::dependencies::{Dependency, DependencyType, ResolvedDependency};
+use crate::providers::ResolvedServer;
+use crate::{ProjectMetadata, Server};
+
+pub(crate) const LOCKFILE_NAME: &str = "crafty.lock";
+
+const LOCKFILE_HEADER: &str = "# This file is generated by Crafty, do not edit it manually.
+# It should be committed so every install uses the exact same files.
+";
+
+/// Exact sources and hashes resolved for the server and dependencies of a project
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct Lockfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<LockedServer>,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, LockedDependency>,
+}
+
+/// The server as declared in `crafty.yml` and what it was resolved to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct LockedServer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brand: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub resolved: ResolvedServer,
+}
+
+/// A dependency as declared in `crafty.yml` and what it was resolved to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct LockedDependency {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    #[serde(rename = "type", default)]
+    pub dependency_type: DependencyType,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub extract: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(rename = "strip-components", default, skip_serializing_if = "is_zero")]
+    pub strip_components: usize,
+    pub resolved: ResolvedDependency,
+}
+
+impl Lockfile {
+    pub fn load(project_directory: &Path) -> anyhow::Result<Option<Lockfile>> {
+        let path = project_directory.join(LOCKFILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path).context("Could not read lockfile")?;
+        let lockfile = serde_yaml::from_str(&contents)
+            .with_context(|| format!("The file \"{}\" is invalid.", LOCKFILE_NAME))?;
+
+        Ok(Some(lockfile))
+    }
+
+    pub fn save(&self, project_directory: &Path) -> anyhow::Result<()> {
+        let contents = serde_yaml::to_string(&self)?;
+        fs::write(
+            project_directory.join(LOCKFILE_NAME),
+            format!("{}{}", LOCKFILE_HEADER, contents),
+        )
+        .context("Could not save lockfile")
+    }
+
+    /// Returns the locked server if it still matches the one declared in `crafty.yml`
+    pub fn locked_server(&self, server: &Server) -> Option<ResolvedServer> {
+        self.server
+            .as_ref()
+            .filter(|locked| locked.matches(server))
+            .map(|locked| unverified_if_local(&locked.resolved))
+    }
+
+    /// Returns the locked dependency if it still matches the one declared in `crafty.yml`
+    pub fn locked_dependency(
+        &self,
+        id: &str,
+        dependency: &Dependency,
+    ) -> Option<ResolvedDependency> {
+        self.dependencies
+            .get(id)
+            .filter(|locked| locked.matches(dependency))
+            .map(|locked| unverified_if_local(&locked.resolved))
+    }
+
+    /// Returns a list of reasons why the lockfile does not match the project, if any
+    pub fn outdated_entries(&self, project: &ProjectMetadata) -> Vec<String> {
+        let mut reasons = vec![];
+
+        if self.locked_server(&project.server).is_none() {
+            reasons.push("the server has changed".to_string());
+        }
+
+        for (id, dependency) in &project.dependencies {
+            match self.dependencies.get(id) {
+                Some(locked) if !locked.matches(dependency) => {
+                    reasons.push(format!("dependency \"{}\" has changed", id));
+                }
+                // Dependencies without a source are never locked, nor are optional ones that could not be installed
+                None if dependency.required && is_lockable(dependency) => {
+                    reasons.push(format!("dependency \"{}\" is missing", id));
+                }
+                _ => {}
+            }
+        }
+
+        for id in self.dependencies.keys() {
+            if !project.dependencies.contains_key(id) {
+                reasons.push(format!("dependency \"{}\" was removed", id));
+            }
+        }
+
+        reasons
+    }
+}
+
+/// A file the server or a dependency was resolved to
+trait ResolvedFile: Clone {
+    fn url(&self) -> &str;
+    fn clear_sha256(&mut self);
+}
+
+impl ResolvedFile for ResolvedServer {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn clear_sha256(&mut self) {
+        self.sha256 = None;
+    }
+}
+
+impl ResolvedFile for ResolvedDependency {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn clear_sha256(&mut self) {
+        self.sha256 = None;
+    }
+}
+
+/// Local files are expected to change, so their locked checksum is not verified
+fn unverified_if_local<R: ResolvedFile>(resolved: &R) -> R {
+    let mut resolved = resolved.clone();
+    if !utils::is_url(resolved.url()) {
+        resolved.clear_sha256();
+    }
+    resolved
+}
+
+impl LockedServer {
+    pub fn new(server: &Server, resolved: ResolvedServer) -> Self {
+        Self {
+            source: server.source.clone().filter(|source| !source.is_empty()),
+            brand: server.brand.clone(),
+            version: server.version.clone(),
+            resolved,
+        }
+    }
+
+    fn matches(&self, server: &Server) -> bool {
+        self.source.as_ref() == server.source.as_ref().filter(|source| !source.is_empty())
+            && self.brand == server.brand
+            && self.version == server.version
+            && (server.build.is_none() || self.resolved.build == server.build)
+            && (server.loader_version.is_none()
+                || self.resolved.loader_version == server.loader_version)
+    }
+}
+
+impl LockedDependency {
+    pub fn new(dependency: &Dependency, resolved: ResolvedDependency) -> Self {
+        Self {
+            source: dependency.source.clone(),
+            version: dependency.version.clone(),
+            asset: dependency.asset.clone(),
+            artifact: dependency.artifact.clone(),
+            build: dependency.build.clone(),
+            dependency_type: dependency.dependency_type.clone(),
+            extract: dependency.extract,
+            include: dependency.include.clone(),
+            strip_components: dependency.strip_components,
+            resolved,
+        }
+    }
+
+    fn matches(&self, dependency: &Dependency) -> bool {
+        self.source == dependency.source
+            && self.version == dependency.version
+            && self.asset == dependency.asset
+            && self.artifact == dependency.artifact
+            && self.build == dependency.build
+            && self.dependency_type == dependency.dependency_type
+            && self.extract == dependency.extract
+            && self.include == dependency.include
+            && self.strip_components == dependency.strip_components
+    }
+}
+
+/// Dependencies without a source nor a version have nothing to resolve
+fn is_lockable(dependency: &Dependency) -> bool {
+    dependency.source.is_some() || dependency.version.is_some()
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(yaml: &str) -> ProjectMetadata {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn lockfile(project: &ProjectMetadata) -> Lockfile {
+        let resolved = |url: &str| ResolvedDependency {
+            url: url.into(),
+            sha256: Some("aa".into()),
+            ..Default::default()
+        };
+
+        Lockfile {
+            server: Some(LockedServer::new(
+                &project.server,
+                ResolvedServer {
+                    url: "https://example.com/paper.jar".into(),
+                    version: "1.20.4".into(),
+                    build: Some(10),
+                    sha256: Some("bb".into()),
+                    ..Default::default()
+                },
+            )),
+            dependencies: project
+                .dependencies
+                .iter()
+                .map(|(id, dependency)| {
+                    let url = dependency
+                        .source
+                        .as_deref()
+                        .unwrap_or("https://example.com/a.jar");
+                    (id.clone(), LockedDependency::new(dependency, resolved(url)))
+                })
+                .collect(),
+        }
+    }
+
+    const PROJECT: &str = "
+name: test
+server:
+  brand: paper
+  version: 1.20.4
+dependencies:
+  luckperms:
+    version: 5.4.0
+  local:
+    source: jars/local.jar
+";
+
+    #[test]
+    fn returns_matching_entries() {
+        let project = project(PROJECT);
+        let lockfile = lockfile(&project);
+
+        let server = lockfile.locked_server(&project.server).unwrap();
+        assert_eq!(server.build, Some(10));
+        assert_eq!(server.sha256.as_deref(), Some("bb"));
+
+        let luckperms = &project.dependencies["luckperms"];
+        let resolved = lockfile.locked_dependency("luckperms", luckperms).unwrap();
+        assert_eq!(resolved.sha256.as_deref(), Some("aa"));
+        assert!(lockfile.outdated_entries(&project).is_empty());
+    }
+
+    #[test]
+    fn does_not_verify_local_files() {
+        let project = project(PROJECT);
+        let lockfile = lockfile(&project);
+
+        let local = &project.dependencies["local"];
+        let resolved = lockfile.locked_dependency("local", local).unwrap();
+        assert_eq!(resolved.url, "jars/local.jar");
+        assert_eq!(resolved.sha256, None);
+    }
+
+    #[test]
+    fn ignores_changed_entries() {
+        let locked_project = project(PROJECT);
+        let lockfile = lockfile(&locked_project);
+
+        let mut project = locked_project.clone();
+        project.server.version = Some("1.20.6".into());
+        project.dependencies.get_mut("luckperms").unwrap().version = Some("5.4.1".into());
+        project.dependencies.remove("local");
+
+        assert!(lockfile.locked_server(&project.server).is_none());
+        let luckperms = &project.dependencies["luckperms"];
+        assert!(lockfile.locked_dependency("luckperms", luckperms).is_none());
+        assert_eq!(
+            lockfile.outdated_entries(&project),
+            vec![
+                "the server has changed".to_string(),
+                "dependency \"luckperms\" has changed".to_string(),
+                "dependency \"local\" was removed".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_entries_with_changed_install_options() {
+        let locked_project = project(PROJECT);
+        let lockfile = lockfile(&locked_project);
+
+        let mut project = locked_project.clone();
+        let luckperms = project.dependencies.get_mut("luckperms").unwrap();
+        luckperms.extract = true;
+        luckperms.include = vec!["**/*.jar".into()];
+        assert!(lockfile.locked_dependency("luckperms", luckperms).is_none());
+
+        let local = project.dependencies.get_mut("local").unwrap();
+        local.dependency_type = DependencyType::Mod;
+        assert!(lockfile.locked_dependency("local", local).is_none());
+    }
+
+    #[test]
+    fn does_not_require_unlockable_entries() {
+        let locked_project = project(PROJECT);
+        let lockfile = lockfile(&locked_project);
+
+        let extra = project(
+            "
+name: test
+server:
+  brand: paper
+dependencies:
+  sourceless:
+    required: true
+  optional:
+    version: 1.0.0
+  required:
+    version: 1.0.0
+    required: true
+",
+        )
+        .dependencies;
+        let mut project = locked_project.clone();
+        project.dependencies.extend(extra);
+
+        assert_eq!(
+            lockfile.outdated_entries(&project),
+            vec!["dependency \"required\" is missing".to_string()]
+        );
+    }
+
+    #[test]
+    fn pinned_builds_must_match() {
+        let locked_project = project(PROJECT);
+        let lockfile = lockfile(&locked_project);
+
+        let mut server = locked_project.server.clone();
+        server.build = Some(10);
+        assert!(lockfile.locked_server(&server).is_some());
+        server.build = Some(11);
+        assert!(lockfile.locked_server(&server).is_none());
+    }
+}
@@ -1,11 +1,14 @@
 use std::collections::HashMap;
-use std::fs;
+use std::fs::{self, File};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
 
-use crate::dependencies::{DependencyFile, DependencyType};
+use crate::dependencies::{DependencyFile, DependencyType, LIBRARIES_DIRECTORY};
+use crate::descriptor::Descriptor;
 
 pub trait Manifest {
     type ManifestType;
@@ -18,6 +21,25 @@ pub trait Manifest {
 pub struct VersionManifest {
     pub jar_file: String,
     pub source: String,
+    /// SHA-256 checksum of the downloaded server JAR
+    #[serde(default)]
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brand: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installer_version: Option<String>,
+    /// Files generated by a server installer, they are copied to the server directory before running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_directory: Option<String>,
+    /// Unix argument file used to launch the server instead of `-jar`, relative to the server directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args_file: Option<String>,
 
     #[serde(skip_serializing, skip_deserializing)]
     pub versions_directory: PathBuf,
@@ -28,9 +50,82 @@ impl VersionManifest {
         Self {
             jar_file: jar_path.into_os_string().into_string().unwrap(),
             source: source.to_string(),
+            hash: String::new(),
+            brand: None,
+            version: None,
+            build: None,
+            loader_version: None,
+            installer_version: None,
+            install_directory: None,
+            args_file: None,
             versions_directory: Default::default(),
         }
     }
+
+    /// Arguments passed to java to launch the server,
+    /// the argument file is used instead of the given JAR if the server was generated by an installer.
+    ///
+    /// Libraries (relative to the server directory) are added to the classpath,
+    /// the server is then launched with the main class of its JAR instead of `-jar`
+    pub fn launch_arguments(
+        &self,
+        server_jar: &str,
+        libraries: &[String],
+        windows: bool,
+    ) -> anyhow::Result<Vec<String>> {
+        if self.args_file.is_some() && !libraries.is_empty() {
+            log::warn!("Libraries cannot be added to the classpath of servers launched with an argument file");
+        }
+
+        let arguments = match &self.args_file {
+            Some(args_file) if windows => vec![format!(
+                "@{}",
+                args_file.replace("unix_args.txt", "win_args.txt")
+            )],
+            Some(args_file) => vec![format!("@{}", args_file)],
+            None if libraries.is_empty() => vec!["-jar".into(), server_jar.into()],
+            None => {
+                log::info!("Launching the server with its main class to add the libraries to the classpath");
+                let separator = if windows { ";" } else { ":" };
+                let classpath = std::iter::once(server_jar)
+                    .chain(libraries.iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(separator);
+
+                vec!["-cp".into(), classpath, self.main_class()?]
+            }
+        };
+
+        Ok(arguments)
+    }
+
+    /// Main class of the server JAR, read from its manifest
+    fn main_class(&self) -> anyhow::Result<String> {
+        let mut archive = ZipArchive::new(File::open(&self.jar_file)?)
+            .with_context(|| format!("Could not open the server JAR \"{}\"", self.jar_file))?;
+        let mut manifest = String::new();
+        archive
+            .by_name("META-INF/MANIFEST.MF")?
+            .read_to_string(&mut manifest)?;
+
+        manifest
+            .lines()
+            .find_map(|line| line.strip_prefix("Main-Class:"))
+            .map(|main_class| main_class.trim().to_string())
+            .ok_or_else(|| {
+                anyhow!(
+                    "The server JAR \"{}\" has no main class, libraries cannot be added to its classpath",
+                    self.jar_file
+                )
+            })
+    }
+
+    pub fn jar_file_name(&self) -> &str {
+        Path::new(&self.jar_file)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("server.jar")
+    }
 }
 
 impl Manifest for VersionManifest {
@@ -57,7 +152,37 @@ impl Manifest for VersionManifest {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DependencyDetails {
     pub dependency_type: DependencyType,
+    /// Directory the files are installed in, relative to the server directory, for `custom` dependencies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Version the dependency was resolved to (eg. release tag, build number or git commit), if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Archive the files were extracted from, it is removed once extracted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<DependencyFile>,
     pub files: Vec<DependencyFile>,
+    /// Descriptor of the plugin or mod (eg. `plugin.yml`), if one was found in its files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descriptor: Option<Descriptor>,
+    /// Profiles the dependency is used in, every profile if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+}
+
+impl DependencyDetails {
+    /// Path of an installed file relative to the directory it is placed in on the server,
+    /// files extracted from an archive keep their path relative to it
+    pub(crate) fn server_path(&self, id: &str, file: &DependencyFile) -> PathBuf {
+        let filename = Path::new(&file.filename);
+        match &self.archive {
+            Some(_) => filename.strip_prefix(id).unwrap_or(filename).to_path_buf(),
+            None => filename
+                .file_name()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| filename.to_path_buf()),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -67,6 +192,41 @@ impl DependenciesManifest {
     pub fn new(dependencies: HashMap<String, DependencyDetails>) -> Self {
         Self(dependencies)
     }
+
+    /// Keeps the dependencies used in the given profile, dependencies limited to some profiles
+    /// are removed if no profile is selected
+    pub fn for_profile(mut self, profile_name: Option<&str>) -> Self {
+        self.0.retain(|_, details| {
+            details.profiles.is_empty()
+                || profile_name.is_some_and(|profile_name| {
+                    details
+                        .profiles
+                        .iter()
+                        .any(|profile| profile == profile_name)
+                })
+        });
+        self
+    }
+
+    /// Paths of the library files added to the classpath, relative to the server directory
+    pub fn libraries(&self) -> Vec<String> {
+        let mut libraries: Vec<String> = self
+            .0
+            .iter()
+            .filter(|(_, details)| details.dependency_type == DependencyType::Library)
+            .flat_map(|(id, details)| {
+                details
+                    .files
+                    .iter()
+                    .map(move |file| details.server_path(id, file))
+            })
+            // Archives can contain other files than JAR files (eg. licenses)
+            .filter(|path| path.extension().is_some_and(|extension| extension == "jar"))
+            .map(|path| format!("{}/{}", LIBRARIES_DIRECTORY, path.to_string_lossy()))
+            .collect();
+        libraries.sort();
+        libraries
+    }
 }
 
 impl Manifest for DependenciesManifest {
@@ -88,3 +248,25 @@ impl Manifest for DependenciesManifest {
         fs::write(directory, parsed_manifest).context("Could not save manifest file")
     }
 }
+
+/// Files Crafty placed in each server directory (eg. `server` or `out/server`, relative to the project),
+/// so they can be removed once they are no longer needed without touching other files
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ServerState(pub(crate) HashMap<String, Vec<String>>);
+
+impl Manifest for ServerState {
+    type ManifestType = ServerState;
+
+    fn load_manifest(project_directory: &Path) -> anyhow::Result<Self::ManifestType> {
+        let data_directory = project_directory.join(".crafty");
+        let contents = fs::read_to_string(data_directory.join("server-state.yml"))
+            .context("Could not find server state file")?;
+
+        serde_yaml::from_str(&contents).context("Could not parse server state file")
+    }
+
+    fn save_manifest(&self, directory: &Path) -> anyhow::Result<()> {
+        let parsed_manifest = serde_yaml::to_string(&self.0)?;
+        fs::write(directory, parsed_manifest).context("Could not save server state file")
+    }
+}
@@ -25,7 +25,7 @@ pub fn pack_server<P: AsRef<Path>>(
     let project = load_project(root_directory)?;
     let project_directory = &project.root_directory;
 
-    let settings = match project.get_settings(profile_name) {
+    let settings = match project.get_settings(profile_name.clone()) {
         Ok(settings) => settings,
         Err(_) => {
             log::warn!("No settings file was found, using default values...");
@@ -35,28 +35,31 @@ pub fn pack_server<P: AsRef<Path>>(
     let version = project
         .get_manifest::<VersionManifest>()
         .context("Version manifest file was not found, make sure to run `crafty install` first")?;
-    let dependencies = project.get_manifest::<DependenciesManifest>().context(
-        "Dependency manifest file was not found, make sure to run `crafty install` first",
-    )?;
+    let dependencies = project
+        .get_manifest::<DependenciesManifest>()
+        .context("Dependency manifest file was not found, make sure to run `crafty install` first")?
+        .for_profile(profile_name.as_deref());
 
     log::info!("Preparing server files...");
     fs::create_dir_all(&server_directory)?;
+    let libraries = dependencies.libraries();
 
-    dependencies::prepare_server_dependencies(
-        dependencies,
-        &project_directory.join(".crafty").join("dependencies"),
-        &server_directory,
-    )?;
-
+    // Files are processed first, data packs are installed in the world set in `server.properties`
     process_files(project_directory, &server_directory, settings.clone())?;
 
-    let server_jar = Path::new(&version.jar_file);
-    let server_jar_name: &str = Path::new(&version.jar_file)
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("server.jar");
-    fs::copy(server_jar, server_directory.join(server_jar_name))
-        .context("Could not copy server JAR file")?;
+    dependencies::prepare_server_dependencies(dependencies, project_directory, &server_directory)?;
+
+    if let Some(install_directory) = &version.install_directory {
+        common::utils::copy_directory(install_directory, &server_directory)
+            .context("Could not copy installed server files")?;
+    }
+
+    let server_jar_name = version.jar_file_name();
+    // Servers launched with an argument file are already copied from the install directory
+    if version.args_file.is_none() {
+        fs::copy(&version.jar_file, server_directory.join(server_jar_name))
+            .context("Could not copy server JAR file")?;
+    }
 
     log::info!("Generating start scripts...");
     let java_path = match env::var("JAVA_BIN_PATH") {
@@ -66,7 +69,8 @@ pub fn pack_server<P: AsRef<Path>>(
     generate_start_scripts(
         server_directory.as_path(),
         &java_path,
-        server_jar_name,
+        &version,
+        &libraries,
         settings,
     )?;
 
@@ -83,20 +87,21 @@ pub fn pack_server<P: AsRef<Path>>(
 fn generate_start_scripts(
     server_directory: &Path,
     jar_path: &str,
-    server_jar: &str,
+    version: &VersionManifest,
+    libraries: &[String],
     settings: ProjectSettings,
 ) -> anyhow::Result<()> {
     fn inner(
         out_path: PathBuf,
         contents: &str,
         jar_path: &str,
-        server_jar: &str,
+        launch_arguments: Vec<String>,
         settings: &ProjectSettings,
     ) -> anyhow::Result<()> {
         let contents = contents
             .replace("{jar_path}", jar_path)
             .replace("{jvm_options}", &settings.jvm_options.join(" "))
-            .replace("{server_jar}", server_jar)
+            .replace("{launch_args}", &launch_arguments.join(" "))
             .replace("{server_args}", &settings.server_args.join(" "));
 
         let mut bash_file = File::create(out_path)?;
@@ -107,26 +112,26 @@ fn generate_start_scripts(
     let bash_script = r#"#!/bin/bash
 # Script generated by Crafty
 
-{jar_path} {jvm_options} -jar {server_jar} {server_args}
+{jar_path} {jvm_options} {launch_args} {server_args}
 "#;
     let batch_script = r#"@echo off
 :: Script generated by Crafty
 
-{jar_path} {jvm_options} -jar {server_jar} {server_args}
+{jar_path} {jvm_options} {launch_args} {server_args}
 "#;
 
     inner(
         server_directory.join("start.sh"),
         bash_script,
         jar_path,
-        server_jar,
+        version.launch_arguments(version.jar_file_name(), libraries, false)?,
         &settings,
     )?;
     inner(
         server_directory.join("start.bat"),
         batch_script,
         jar_path,
-        server_jar,
+        version.launch_arguments(version.jar_file_name(), libraries, true)?,
         &settings,
     )?;
     Ok(())
@@ -0,0 +1,236 @@
+use std::env;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+use super::{ResolvedServer, ServerProvider};
+use crate::installer::Downloader;
+use crate::Server;
+
+const FABRIC_META_URL: &str = "https://meta.fabricmc.net";
+
+#[derive(Deserialize, Debug)]
+struct LoaderEntry {
+    loader: Component,
+}
+
+#[derive(Deserialize, Debug)]
+struct Component {
+    version: String,
+    #[serde(default)]
+    stable: bool,
+}
+
+/// Provider for Fabric servers, using the server launcher JAR provided by the Fabric meta API
+pub(super) struct FabricProvider {
+    meta_url: String,
+    downloader: Downloader,
+}
+
+impl FabricProvider {
+    /// The meta API base URL can be overridden with the `CRAFTY_FABRIC_META_URL` variable
+    pub fn new(downloader: Downloader) -> Self {
+        let meta_url =
+            env::var("CRAFTY_FABRIC_META_URL").unwrap_or_else(|_| FABRIC_META_URL.into());
+
+        Self {
+            meta_url: meta_url.trim_end_matches('/').to_string(),
+            downloader,
+        }
+    }
+}
+
+impl ServerProvider for FabricProvider {
+    /// Resolves the latest stable loader (unless pinned with `loader-version`)
+    /// and installer for the given version
+    async fn resolve(&self, version: &str, server: &Server) -> anyhow::Result<ResolvedServer> {
+        log::info!("Resolving Fabric {} server...", version);
+
+        let loaders = self
+            .downloader
+            .fetch_json::<Vec<LoaderEntry>>(&format!(
+                "{}/v2/versions/loader/{}",
+                self.meta_url, version
+            ))
+            .await?;
+        let loaders: Vec<Component> = loaders.into_iter().map(|entry| entry.loader).collect();
+        let loader_version = select_version(&loaders, server.loader_version.as_deref())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Could not find Fabric loader \"{}\" for version \"{}\"",
+                    server.loader_version.as_deref().unwrap_or("latest"),
+                    version
+                )
+            })?;
+
+        let installers = self
+            .downloader
+            .fetch_json::<Vec<Component>>(&format!("{}/v2/versions/installer", self.meta_url))
+            .await?;
+        let installer_version = select_version(&installers, None)
+            .ok_or_else(|| anyhow!("Could not find a Fabric installer version"))?;
+
+        Ok(ResolvedServer {
+            url: format!(
+                "{}/v2/versions/loader/{}/{}/{}/server/jar",
+                self.meta_url, version, loader_version, installer_version
+            ),
+            file_name: Some(format!(
+                "fabric-server-mc.{}-loader.{}-launcher.{}.jar",
+                version, loader_version, installer_version
+            )),
+            version: version.to_string(),
+            loader_version: Some(loader_version),
+            installer_version: Some(installer_version),
+            ..Default::default()
+        })
+    }
+
+    async fn latest_version(&self) -> anyhow::Result<Option<String>> {
+        let versions = self
+            .downloader
+            .fetch_json::<Vec<Component>>(&format!("{}/v2/versions/game", self.meta_url))
+            .await?;
+
+        Ok(versions
+            .into_iter()
+            .find(|component| component.stable)
+            .map(|component| component.version))
+    }
+}
+
+/// Returns the pinned version if it exists, otherwise the latest stable version,
+/// falling back to the latest version if none is stable
+fn select_version(components: &[Component], pinned: Option<&str>) -> Option<String> {
+    if let Some(pinned) = pinned {
+        return components
+            .iter()
+            .find(|component| component.version == pinned)
+            .map(|component| component.version.clone());
+    }
+
+    components
+        .iter()
+        .find(|component| component.stable)
+        .or(components.first())
+        .map(|component| component.version.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::install_with;
+    use crate::InstallOptions;
+
+    fn provider(meta_url: &str) -> FabricProvider {
+        FabricProvider {
+            meta_url: meta_url.into(),
+            downloader: Downloader::new(false),
+        }
+    }
+
+    fn server(yaml: &str) -> Server {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    async fn meta() -> mockito::ServerGuard {
+        let mut meta = mockito::Server::new_async().await;
+        meta.mock("GET", "/v2/versions/loader/1.20.4")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"[
+                    {"loader": {"version": "0.16.0-beta.1", "stable": false}},
+                    {"loader": {"version": "0.15.11", "stable": true}},
+                    {"loader": {"version": "0.15.10", "stable": true}}
+                ]"#,
+            )
+            .create_async()
+            .await;
+        meta.mock("GET", "/v2/versions/installer")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"[{"version": "1.0.2", "stable": false}, {"version": "1.0.1", "stable": true}]"#,
+            )
+            .create_async()
+            .await;
+        meta
+    }
+
+    #[tokio::test]
+    async fn resolves_the_latest_stable_loader_and_installer() {
+        let meta = meta().await;
+
+        let resolved = provider(&meta.url())
+            .resolve("1.20.4", &server("version: 1.20.4"))
+            .await
+            .unwrap();
+
+        assert_eq!(resolved.loader_version.as_deref(), Some("0.15.11"));
+        assert_eq!(resolved.installer_version.as_deref(), Some("1.0.1"));
+        assert_eq!(
+            resolved.url,
+            format!(
+                "{}/v2/versions/loader/1.20.4/0.15.11/1.0.1/server/jar",
+                meta.url()
+            )
+        );
+        assert_eq!(
+            resolved.file_name.as_deref(),
+            Some("fabric-server-mc.1.20.4-loader.0.15.11-launcher.1.0.1.jar")
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_pinned_loaders() {
+        let meta = meta().await;
+        let provider = provider(&meta.url());
+
+        let pinned = provider
+            .resolve(
+                "1.20.4",
+                &server("version: 1.20.4\nloader-version: 0.16.0-beta.1"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(pinned.loader_version.as_deref(), Some("0.16.0-beta.1"));
+
+        let missing = provider
+            .resolve("1.20.4", &server("version: 1.20.4\nloader-version: 0.1.0"))
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn installs_the_server_launcher() {
+        let mut meta = meta().await;
+        let download = meta
+            .mock("GET", "/v2/versions/loader/1.20.4/0.15.11/1.0.1/server/jar")
+            .with_body("launcher")
+            .create_async()
+            .await;
+        let directory = tempfile::tempdir().unwrap();
+        let server = server("brand: fabric\nversion: 1.20.4");
+
+        let (manifest, resolved) = install_with(
+            provider(&meta.url()),
+            "1.20.4",
+            &server,
+            directory.path(),
+            None,
+            &InstallOptions::default(),
+            &Downloader::new(false),
+        )
+        .await
+        .unwrap();
+
+        download.assert_async().await;
+        let jar_file = directory
+            .path()
+            .join(".crafty/versions/fabric-server-mc.1.20.4-loader.0.15.11-launcher.1.0.1.jar");
+        assert_eq!(manifest.jar_file, jar_file.to_string_lossy());
+        assert_eq!(std::fs::read_to_string(jar_file).unwrap(), "launcher");
+        assert_eq!(manifest.loader_version.as_deref(), Some("0.15.11"));
+        assert_eq!(manifest.installer_version.as_deref(), Some("1.0.1"));
+        assert_eq!(resolved.sha256, Some(manifest.hash));
+    }
+}
@@ -0,0 +1,472 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+use super::{InstalledServer, ResolvedServer, ServerProvider};
+use crate::installer::{self, Downloader};
+use crate::Server;
+
+const FORGE_MAVEN_URL: &str = "https://maven.minecraftforge.net";
+const FORGE_PROMOTIONS_URL: &str =
+    "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+const NEOFORGE_MAVEN_URL: &str = "https://maven.neoforged.net";
+
+#[derive(Deserialize, Debug)]
+struct Promotions {
+    promos: std::collections::HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MavenVersions {
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ForgeKind {
+    Forge,
+    NeoForge,
+}
+
+/// Provider for Forge and NeoForge servers, which do not provide a runnable server JAR,
+/// the installer is run with `--installServer` to generate the libraries and argument files
+pub(super) struct ForgeProvider {
+    kind: ForgeKind,
+    maven_url: String,
+    promotions_url: String,
+    downloader: Downloader,
+}
+
+impl ForgeProvider {
+    /// The URLs can be overridden with the `CRAFTY_FORGE_MAVEN_URL`, `CRAFTY_FORGE_PROMOTIONS_URL`
+    /// and `CRAFTY_NEOFORGE_MAVEN_URL` variables
+    pub fn new(kind: ForgeKind, downloader: Downloader) -> Self {
+        let maven_url = match kind {
+            ForgeKind::Forge => {
+                env::var("CRAFTY_FORGE_MAVEN_URL").unwrap_or_else(|_| FORGE_MAVEN_URL.into())
+            }
+            ForgeKind::NeoForge => {
+                env::var("CRAFTY_NEOFORGE_MAVEN_URL").unwrap_or_else(|_| NEOFORGE_MAVEN_URL.into())
+            }
+        };
+
+        Self {
+            kind,
+            maven_url: maven_url.trim_end_matches('/').to_string(),
+            promotions_url: env::var("CRAFTY_FORGE_PROMOTIONS_URL")
+                .unwrap_or_else(|_| FORGE_PROMOTIONS_URL.into()),
+            downloader,
+        }
+    }
+
+    fn brand(&self) -> &'static str {
+        match self.kind {
+            ForgeKind::Forge => "forge",
+            ForgeKind::NeoForge => "neoforge",
+        }
+    }
+
+    /// Maven version of the installer artifact
+    fn artifact_version(&self, version: &str, loader_version: &str) -> String {
+        match self.kind {
+            ForgeKind::Forge => format!("{}-{}", version, loader_version),
+            ForgeKind::NeoForge => loader_version.to_string(),
+        }
+    }
+
+    /// Resolves the recommended Forge version for a Minecraft version, or the latest if there is none
+    async fn latest_forge_version(&self, version: &str) -> anyhow::Result<String> {
+        let promotions = self
+            .downloader
+            .fetch_json::<Promotions>(&self.promotions_url)
+            .await?;
+
+        promotions
+            .promos
+            .get(&format!("{}-recommended", version))
+            .or(promotions.promos.get(&format!("{}-latest", version)))
+            .cloned()
+            .ok_or_else(|| anyhow!("Could not find a Forge version for \"{}\"", version))
+    }
+
+    /// Resolves the latest stable NeoForge version for a Minecraft version,
+    /// NeoForge versions drop the major Minecraft version, e.g. `20.4.x` is for `1.20.4`
+    async fn latest_neoforge_version(&self, version: &str) -> anyhow::Result<String> {
+        let versions = self
+            .downloader
+            .fetch_json::<MavenVersions>(&format!(
+                "{}/api/maven/versions/releases/net/neoforged/neoforge",
+                self.maven_url
+            ))
+            .await?
+            .versions;
+
+        let mut parts = version.split('.').skip(1);
+        let prefix = format!(
+            "{}.{}.",
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or("0")
+        );
+        let candidates: Vec<&String> = versions
+            .iter()
+            .filter(|neoforge_version| neoforge_version.starts_with(&prefix))
+            .collect();
+
+        candidates
+            .iter()
+            .rev()
+            .find(|neoforge_version| !neoforge_version.contains('-'))
+            .or(candidates.last())
+            .map(|neoforge_version| neoforge_version.to_string())
+            .ok_or_else(|| anyhow!("Could not find a NeoForge version for \"{}\"", version))
+    }
+
+    /// Latest Minecraft version with a Forge build, taken from the promoted versions
+    async fn latest_forge_game_version(&self) -> anyhow::Result<Option<String>> {
+        let promotions = self
+            .downloader
+            .fetch_json::<Promotions>(&self.promotions_url)
+            .await?;
+
+        Ok(promotions
+            .promos
+            .keys()
+            .filter_map(|key| key.strip_suffix("-latest"))
+            .max_by_key(|version| version_parts(version))
+            .map(|version| version.to_string()))
+    }
+
+    /// Latest Minecraft version with a stable NeoForge build, eg. `1.20.4` for `20.4.x`
+    async fn latest_neoforge_game_version(&self) -> anyhow::Result<Option<String>> {
+        let versions = self
+            .downloader
+            .fetch_json::<MavenVersions>(&format!(
+                "{}/api/maven/versions/releases/net/neoforged/neoforge",
+                self.maven_url
+            ))
+            .await?
+            .versions;
+
+        Ok(versions
+            .iter()
+            .rev()
+            .find(|neoforge_version| !neoforge_version.contains('-'))
+            .map(|neoforge_version| {
+                let mut parts = neoforge_version.split('.');
+                match (parts.next(), parts.next()) {
+                    (Some(major), Some("0") | None) => format!("1.{}", major),
+                    (Some(major), Some(minor)) => format!("1.{}.{}", major, minor),
+                    (None, _) => neoforge_version.to_string(),
+                }
+            }))
+    }
+
+    /// Path of the Unix argument file generated by the installer, relative to the install directory
+    fn args_file(&self, version: &str, loader_version: &str) -> PathBuf {
+        let library = match self.kind {
+            ForgeKind::Forge => Path::new("libraries/net/minecraftforge/forge")
+                .join(format!("{}-{}", version, loader_version)),
+            ForgeKind::NeoForge => {
+                Path::new("libraries/net/neoforged/neoforge").join(loader_version)
+            }
+        };
+
+        library.join("unix_args.txt")
+    }
+
+    /// Finds what the installer generated in the install directory, the argument file or a runnable JAR
+    fn installed_server(
+        &self,
+        resolved: &ResolvedServer,
+        downloaded_jar: PathBuf,
+        install_directory: PathBuf,
+    ) -> anyhow::Result<InstalledServer> {
+        let loader_version = resolved.loader_version.as_deref().unwrap_or_default();
+        let args_file = self.args_file(&resolved.version, loader_version);
+        if install_directory.join(&args_file).is_file() {
+            return Ok(InstalledServer {
+                jar_file: downloaded_jar,
+                install_directory: Some(install_directory),
+                args_file: Some(args_file),
+            });
+        }
+
+        // Older versions do not use argument files, and generate a runnable JAR instead
+        let artifact_version = self.artifact_version(&resolved.version, loader_version);
+        let jar_file = [
+            format!("{}-{}.jar", self.brand(), artifact_version),
+            format!("{}-{}-universal.jar", self.brand(), artifact_version),
+        ]
+        .into_iter()
+        .map(|name| install_directory.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            anyhow!(
+                "Could not find the server generated by the {} installer",
+                self.brand()
+            )
+        })?;
+
+        Ok(InstalledServer {
+            jar_file,
+            install_directory: Some(install_directory),
+            args_file: None,
+        })
+    }
+}
+
+impl ServerProvider for ForgeProvider {
+    async fn resolve(&self, version: &str, server: &Server) -> anyhow::Result<ResolvedServer> {
+        log::info!("Resolving {} {} server...", self.brand(), version);
+
+        let loader_version = match (&server.loader_version, self.kind) {
+            (Some(loader_version), _) => loader_version.clone(),
+            (None, ForgeKind::Forge) => self.latest_forge_version(version).await?,
+            (None, ForgeKind::NeoForge) => self.latest_neoforge_version(version).await?,
+        };
+
+        let artifact_version = self.artifact_version(version, &loader_version);
+        let artifact_path = match self.kind {
+            ForgeKind::Forge => "net/minecraftforge/forge",
+            ForgeKind::NeoForge => "releases/net/neoforged/neoforge",
+        };
+
+        Ok(ResolvedServer {
+            url: format!(
+                "{}/{}/{}/{}-{}-installer.jar",
+                self.maven_url,
+                artifact_path,
+                artifact_version,
+                self.brand(),
+                artifact_version
+            ),
+            version: version.to_string(),
+            loader_version: Some(loader_version),
+            ..Default::default()
+        })
+    }
+
+    async fn latest_version(&self) -> anyhow::Result<Option<String>> {
+        match self.kind {
+            ForgeKind::Forge => self.latest_forge_game_version().await,
+            ForgeKind::NeoForge => self.latest_neoforge_game_version().await,
+        }
+    }
+
+    async fn install(
+        &self,
+        resolved: &ResolvedServer,
+        downloaded_jar: PathBuf,
+        versions_directory: &Path,
+    ) -> anyhow::Result<InstalledServer> {
+        let loader_version = resolved.loader_version.as_deref().unwrap_or_default();
+        // Keyed by loader version, so files of a previous loader are never mixed with the new ones
+        let install_directory = versions_directory.join(format!(
+            "{}-{}-{}",
+            self.brand(),
+            resolved.version,
+            loader_version
+        ));
+
+        log::info!(
+            "Running {} installer, this might take a while...",
+            self.brand()
+        );
+        installer::run_installer(
+            &downloaded_jar,
+            &[
+                "--installServer".into(),
+                install_directory.display().to_string(),
+            ],
+            versions_directory,
+        )
+        .await?;
+
+        self.installed_server(resolved, downloaded_jar, install_directory)
+    }
+}
+
+/// Numeric parts of a version to compare them, eg. `1.20.4` is `[1, 20, 4]`
+fn version_parts(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or_default())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::manifests::VersionManifest;
+
+    const NEOFORGE_VERSIONS: &str =
+        r#"{"versions": ["20.2.88", "20.4.200", "20.4.237", "20.4.238-beta", "21.0.1-beta"]}"#;
+
+    fn provider(kind: ForgeKind, api_url: &str) -> ForgeProvider {
+        ForgeProvider {
+            kind,
+            maven_url: api_url.into(),
+            promotions_url: format!("{}/promotions_slim.json", api_url),
+            downloader: Downloader::new(false),
+        }
+    }
+
+    fn server(yaml: &str) -> Server {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    async fn neoforge_api() -> mockito::ServerGuard {
+        let mut api = mockito::Server::new_async().await;
+        api.mock("GET", "/api/maven/versions/releases/net/neoforged/neoforge")
+            .with_header("content-type", "application/json")
+            .with_body(NEOFORGE_VERSIONS)
+            .create_async()
+            .await;
+        api
+    }
+
+    #[tokio::test]
+    async fn resolves_neoforge_versions_from_the_minecraft_version() {
+        let api = neoforge_api().await;
+        let provider = provider(ForgeKind::NeoForge, &api.url());
+
+        // Stable versions are preferred, betas are only used if there is no stable one
+        let resolved = provider
+            .resolve("1.20.4", &server("version: 1.20.4"))
+            .await
+            .unwrap();
+        assert_eq!(resolved.loader_version.as_deref(), Some("20.4.237"));
+        assert_eq!(
+            resolved.url,
+            format!(
+                "{}/releases/net/neoforged/neoforge/20.4.237/neoforge-20.4.237-installer.jar",
+                api.url()
+            )
+        );
+
+        let resolved = provider
+            .resolve("1.21", &server("version: 1.21"))
+            .await
+            .unwrap();
+        assert_eq!(resolved.loader_version.as_deref(), Some("21.0.1-beta"));
+
+        assert!(provider
+            .resolve("1.20", &server("version: 1.20"))
+            .await
+            .is_err());
+        assert_eq!(
+            provider.latest_version().await.unwrap().as_deref(),
+            Some("1.20.4")
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_recommended_forge_versions() {
+        let mut api = mockito::Server::new_async().await;
+        api.mock("GET", "/promotions_slim.json")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"promos": {
+                    "1.20.4-latest": "49.0.30", "1.20.4-recommended": "49.0.26",
+                    "1.20.6-latest": "50.0.1", "1.9-latest": "12.16.1.1938"
+                }}"#,
+            )
+            .create_async()
+            .await;
+        let provider = provider(ForgeKind::Forge, &api.url());
+
+        let resolved = provider
+            .resolve("1.20.4", &server("version: 1.20.4"))
+            .await
+            .unwrap();
+        assert_eq!(resolved.loader_version.as_deref(), Some("49.0.26"));
+        assert_eq!(
+            resolved.url,
+            format!(
+                "{}/net/minecraftforge/forge/1.20.4-49.0.26/forge-1.20.4-49.0.26-installer.jar",
+                api.url()
+            )
+        );
+
+        let pinned = provider
+            .resolve("1.20.6", &server("version: 1.20.6\nloader-version: 50.0.0"))
+            .await
+            .unwrap();
+        assert_eq!(pinned.loader_version.as_deref(), Some("50.0.0"));
+        assert_eq!(
+            provider.latest_version().await.unwrap().as_deref(),
+            Some("1.20.6")
+        );
+    }
+
+    #[test]
+    fn launches_servers_with_the_generated_argument_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let install_directory = directory.path().join("neoforge-1.20.4-20.4.237");
+        let libraries = install_directory.join("libraries/net/neoforged/neoforge/20.4.237");
+        fs::create_dir_all(&libraries).unwrap();
+        fs::write(
+            libraries.join("unix_args.txt"),
+            "-DlibraryDirectory=libraries",
+        )
+        .unwrap();
+
+        let provider = provider(ForgeKind::NeoForge, "https://example.com");
+        let resolved = ResolvedServer {
+            version: "1.20.4".into(),
+            loader_version: Some("20.4.237".into()),
+            ..Default::default()
+        };
+        let installed = provider
+            .installed_server(&resolved, "installer.jar".into(), install_directory)
+            .unwrap();
+        let args_file = installed.args_file.unwrap();
+        assert_eq!(
+            args_file,
+            Path::new("libraries/net/neoforged/neoforge/20.4.237/unix_args.txt")
+        );
+
+        let mut manifest = VersionManifest::new("https://example.com", installed.jar_file);
+        manifest.args_file = Some(args_file.to_string_lossy().to_string());
+        assert_eq!(
+            manifest
+                .launch_arguments("installer.jar", &[], false)
+                .unwrap(),
+            vec!["@libraries/net/neoforged/neoforge/20.4.237/unix_args.txt"]
+        );
+        assert_eq!(
+            manifest
+                .launch_arguments("installer.jar", &[], true)
+                .unwrap(),
+            vec!["@libraries/net/neoforged/neoforge/20.4.237/win_args.txt"]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_generated_jar() {
+        let directory = tempfile::tempdir().unwrap();
+        let install_directory = directory.path().join("forge-1.12.2-14.23.5.2859");
+        fs::create_dir_all(&install_directory).unwrap();
+
+        let provider = provider(ForgeKind::Forge, "https://example.com");
+        let resolved = ResolvedServer {
+            version: "1.12.2".into(),
+            loader_version: Some("14.23.5.2859".into()),
+            ..Default::default()
+        };
+        assert!(provider
+            .installed_server(&resolved, "installer.jar".into(), install_directory.clone())
+            .is_err());
+
+        let jar = install_directory.join("forge-1.12.2-14.23.5.2859.jar");
+        fs::write(&jar, "").unwrap();
+        let installed = provider
+            .installed_server(&resolved, "installer.jar".into(), install_directory)
+            .unwrap();
+        assert_eq!(installed.jar_file, jar);
+        assert_eq!(installed.args_file, None);
+    }
+}
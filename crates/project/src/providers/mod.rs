@@ -0,0 +1,426 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::checksum::{self, Algorithm};
+use crate::installer::{self, Downloader};
+use crate::manifests::{Manifest, VersionManifest};
+use crate::{InstallOptions, Server};
+use common::utils;
+
+mod fabric;
+mod forge;
+mod paper;
+mod quilt;
+mod vanilla;
+
+/// A server JAR resolved from the brand and version of a project
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ResolvedServer {
+    pub url: String,
+    /// File name to save the download as, defaults to the last segment of the URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    /// Resolved version, aliases like `latest-release` are replaced with the actual version
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installer_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// A server installed by a provider
+pub(crate) struct InstalledServer {
+    pub jar_file: PathBuf,
+    /// Directory with the files generated by an installer (libraries, launchers, etc.)
+    pub install_directory: Option<PathBuf>,
+    /// Argument file to launch the server with instead of `-jar`, relative to the install directory
+    pub args_file: Option<PathBuf>,
+}
+
+/// Resolves and installs the server JAR for a specific brand (or family of brands)
+pub(crate) trait ServerProvider {
+    async fn resolve(&self, version: &str, server: &Server) -> anyhow::Result<ResolvedServer>;
+
+    /// Latest stable Minecraft version supported by the provider, if it can be found
+    async fn latest_version(&self) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Runs any step needed after downloading the resolved JAR,
+    /// by default the downloaded JAR is the server itself
+    async fn install(
+        &self,
+        _resolved: &ResolvedServer,
+        downloaded_jar: PathBuf,
+        _versions_directory: &Path,
+    ) -> anyhow::Result<InstalledServer> {
+        Ok(InstalledServer {
+            jar_file: downloaded_jar,
+            install_directory: None,
+            args_file: None,
+        })
+    }
+}
+
+impl ResolvedServer {
+    /// Version with the build or loader version, eg. `1.20.4 #463` or `1.20.4 (loader 0.15.7)`
+    pub fn display_version(&self) -> String {
+        match (&self.build, &self.loader_version) {
+            (Some(build), _) => format!("{} #{}", self.version, build),
+            (None, Some(loader_version)) => format!("{} (loader {})", self.version, loader_version),
+            (None, None) => self.version.clone(),
+        }
+    }
+}
+
+/// Provider for servers with an explicit `source` (URL or path)
+struct SourceProvider {
+    source: String,
+}
+
+impl ServerProvider for SourceProvider {
+    async fn resolve(&self, version: &str, _server: &Server) -> anyhow::Result<ResolvedServer> {
+        Ok(ResolvedServer {
+            url: self.source.clone(),
+            version: version.to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Provider of the servers resolved from their `brand`
+enum BrandProvider {
+    Paper(paper::PaperProvider),
+    Vanilla(vanilla::VanillaProvider),
+    Fabric(fabric::FabricProvider),
+    Forge(forge::ForgeProvider),
+    Quilt(quilt::QuiltProvider),
+}
+
+impl BrandProvider {
+    /// Returns the provider of a (lowercase) brand, if it is supported
+    fn new(brand: &str, downloader: &Downloader) -> Option<Self> {
+        let downloader = downloader.clone();
+        let provider = match brand {
+            "paper" | "folia" | "velocity" | "waterfall" => {
+                Self::Paper(paper::PaperProvider::new(brand, downloader))
+            }
+            "vanilla" => Self::Vanilla(vanilla::VanillaProvider::new(downloader)),
+            "fabric" => Self::Fabric(fabric::FabricProvider::new(downloader)),
+            "forge" => Self::Forge(forge::ForgeProvider::new(
+                forge::ForgeKind::Forge,
+                downloader,
+            )),
+            "neoforge" => Self::Forge(forge::ForgeProvider::new(
+                forge::ForgeKind::NeoForge,
+                downloader,
+            )),
+            "quilt" => Self::Quilt(quilt::QuiltProvider::new(downloader)),
+            _ => return None,
+        };
+
+        Some(provider)
+    }
+}
+
+impl ServerProvider for BrandProvider {
+    async fn resolve(&self, version: &str, server: &Server) -> anyhow::Result<ResolvedServer> {
+        match self {
+            Self::Paper(provider) => provider.resolve(version, server).await,
+            Self::Vanilla(provider) => provider.resolve(version, server).await,
+            Self::Fabric(provider) => provider.resolve(version, server).await,
+            Self::Forge(provider) => provider.resolve(version, server).await,
+            Self::Quilt(provider) => provider.resolve(version, server).await,
+        }
+    }
+
+    async fn latest_version(&self) -> anyhow::Result<Option<String>> {
+        match self {
+            Self::Paper(provider) => provider.latest_version().await,
+            Self::Vanilla(provider) => provider.latest_version().await,
+            Self::Fabric(provider) => provider.latest_version().await,
+            Self::Forge(provider) => provider.latest_version().await,
+            Self::Quilt(provider) => provider.latest_version().await,
+        }
+    }
+
+    async fn install(
+        &self,
+        resolved: &ResolvedServer,
+        downloaded_jar: PathBuf,
+        versions_directory: &Path,
+    ) -> anyhow::Result<InstalledServer> {
+        match self {
+            Self::Paper(provider) => {
+                provider
+                    .install(resolved, downloaded_jar, versions_directory)
+                    .await
+            }
+            Self::Vanilla(provider) => {
+                provider
+                    .install(resolved, downloaded_jar, versions_directory)
+                    .await
+            }
+            Self::Fabric(provider) => {
+                provider
+                    .install(resolved, downloaded_jar, versions_directory)
+                    .await
+            }
+            Self::Forge(provider) => {
+                provider
+                    .install(resolved, downloaded_jar, versions_directory)
+                    .await
+            }
+            Self::Quilt(provider) => {
+                provider
+                    .install(resolved, downloaded_jar, versions_directory)
+                    .await
+            }
+        }
+    }
+}
+
+/// Installs the server of a project, if a previously resolved server is provided (eg. from the lockfile),
+/// it is installed as is instead of being resolved again
+pub(crate) async fn install_server(
+    server: &Server,
+    root_directory: &Path,
+    locked: Option<ResolvedServer>,
+    options: &InstallOptions,
+    downloader: &Downloader,
+) -> anyhow::Result<(VersionManifest, ResolvedServer)> {
+    if let Some(source) = server.source.as_deref().filter(|source| !source.is_empty()) {
+        let provider = SourceProvider {
+            source: source.to_string(),
+        };
+        let version = server.version.clone().unwrap_or_default();
+        return install_with(
+            provider,
+            &version,
+            server,
+            root_directory,
+            locked,
+            options,
+            downloader,
+        )
+        .await;
+    }
+
+    let brand = server
+        .brand
+        .as_deref()
+        .filter(|brand| !brand.is_empty())
+        .ok_or_else(|| anyhow!("No server source or brand was provided in \"crafty.yml\""))?
+        .to_lowercase();
+    let version = server
+        .version
+        .as_deref()
+        .filter(|version| !version.is_empty())
+        .ok_or_else(|| {
+            anyhow!(
+                "A server version is required to resolve the \"{}\" server",
+                brand
+            )
+        })?;
+
+    let provider = BrandProvider::new(&brand, downloader).ok_or_else(|| {
+        anyhow!(
+            "The server brand \"{}\" is not supported, please provide a server source instead",
+            brand
+        )
+    })?;
+    install_with(
+        provider,
+        version,
+        server,
+        root_directory,
+        locked,
+        options,
+        downloader,
+    )
+    .await
+}
+
+/// Resolves the latest server for the newest Minecraft version and the latest one compatible with
+/// the project version (ignoring pinned builds and loaders), servers with a `source` cannot be checked
+pub(crate) async fn latest_servers(
+    server: &Server,
+    downloader: &Downloader,
+) -> anyhow::Result<(Option<ResolvedServer>, Option<ResolvedServer>)> {
+    if server
+        .source
+        .as_deref()
+        .is_some_and(|source| !source.is_empty())
+    {
+        return Ok((None, None));
+    }
+
+    let brand = server.brand.as_deref().unwrap_or_default().to_lowercase();
+    let version = server.version.as_deref().unwrap_or_default();
+
+    match BrandProvider::new(&brand, downloader) {
+        Some(provider) => latest_with(provider, version, server).await,
+        None => Ok((None, None)),
+    }
+}
+
+async fn latest_with<P: ServerProvider>(
+    provider: P,
+    version: &str,
+    server: &Server,
+) -> anyhow::Result<(Option<ResolvedServer>, Option<ResolvedServer>)> {
+    let unpinned = Server {
+        build: None,
+        loader_version: None,
+        ..server.clone()
+    };
+
+    let compatible = match version {
+        "" => None,
+        version => Some(provider.resolve(version, &unpinned).await?),
+    };
+    let latest = match provider.latest_version().await? {
+        Some(latest)
+            if compatible
+                .as_ref()
+                .is_some_and(|resolved| resolved.version == latest) =>
+        {
+            compatible.clone()
+        }
+        Some(latest) => Some(provider.resolve(&latest, &unpinned).await?),
+        None => None,
+    };
+
+    Ok((latest, compatible))
+}
+
+/// Returns the manifest of the installed server if it matches the resolved one
+/// and the downloaded file is still on disk unchanged
+fn installed_manifest(
+    resolved: &ResolvedServer,
+    server: &Server,
+    root_directory: &Path,
+) -> anyhow::Result<Option<VersionManifest>> {
+    let Ok(manifest) = VersionManifest::load_manifest(root_directory) else {
+        return Ok(None);
+    };
+
+    let matches = manifest.source == resolved.url
+        && manifest.brand == server.brand
+        && manifest.version.as_deref().unwrap_or_default() == resolved.version
+        && manifest.build == resolved.build
+        && manifest.loader_version == resolved.loader_version
+        && (resolved.sha256.is_none() || resolved.sha256.as_ref() == Some(&manifest.hash));
+    let installed = Path::new(&manifest.jar_file).is_file()
+        && manifest
+            .install_directory
+            .iter()
+            .all(|directory| Path::new(directory).is_dir());
+    if !matches || !installed || manifest.hash.is_empty() {
+        return Ok(None);
+    }
+
+    // Installers generate a different JAR, so the downloaded file is checked instead
+    let file_name = match &resolved.file_name {
+        Some(file_name) => file_name.clone(),
+        None if utils::is_url(&resolved.url) => utils::get_filename_from_url(&resolved.url),
+        None => match Path::new(&resolved.url).file_name() {
+            Some(file_name) => file_name.to_string_lossy().to_string(),
+            None => return Ok(None),
+        },
+    };
+    let downloaded_jar = versions_directory(root_directory).join(file_name);
+    let source = installer::local_source(root_directory, &resolved.url);
+    if !checksum::is_unchanged(&downloaded_jar, &source, &manifest.hash)? {
+        return Ok(None);
+    }
+    // The checksums pinned in `crafty.yml` may have been added or changed since the install
+    checksum::verify_declared(
+        &downloaded_jar,
+        server.sha256.as_deref(),
+        server.sha512.as_deref(),
+    )?;
+
+    Ok(Some(manifest))
+}
+
+fn versions_directory(root_directory: &Path) -> PathBuf {
+    root_directory.join(".crafty").join("versions")
+}
+
+async fn install_with<P: ServerProvider>(
+    provider: P,
+    version: &str,
+    server: &Server,
+    root_directory: &Path,
+    locked: Option<ResolvedServer>,
+    options: &InstallOptions,
+    downloader: &Downloader,
+) -> anyhow::Result<(VersionManifest, ResolvedServer)> {
+    let mut resolved = match locked {
+        Some(locked) => locked,
+        None => provider.resolve(version, server).await?,
+    };
+
+    if !options.force {
+        if let Some(manifest) = installed_manifest(&resolved, server, root_directory)? {
+            log::info!("Server \"{}\" is up to date", manifest.jar_file_name());
+            resolved.sha256 = Some(manifest.hash.clone());
+            return Ok((manifest, resolved));
+        }
+    }
+
+    let versions_directory = versions_directory(root_directory);
+    let downloaded_jar = downloader
+        .download_server(
+            &installer::local_source(root_directory, &resolved.url),
+            resolved.file_name.as_deref(),
+            resolved.sha256.as_deref(),
+            versions_directory.clone(),
+        )
+        .await?;
+
+    if let Some(sha1) = &resolved.sha1 {
+        checksum::verify(&downloaded_jar, Algorithm::Sha1, sha1)?;
+    }
+    if let Some(sha256) = &resolved.sha256 {
+        checksum::verify(&downloaded_jar, Algorithm::Sha256, sha256)?;
+    }
+    checksum::verify_declared(
+        &downloaded_jar,
+        server.sha256.as_deref(),
+        server.sha512.as_deref(),
+    )?;
+    let hash = checksum::file_hash(&downloaded_jar, Algorithm::Sha256)?;
+
+    let installed = provider
+        .install(&resolved, downloaded_jar, &versions_directory)
+        .await?;
+
+    let mut manifest = VersionManifest::new(&resolved.url, installed.jar_file);
+    manifest.hash = hash;
+    manifest.brand = server.brand.clone();
+    manifest.version = Some(resolved.version.clone()).filter(|version| !version.is_empty());
+    manifest.build = resolved.build;
+    manifest.loader_version = resolved.loader_version.clone();
+    manifest.installer_version = resolved.installer_version.clone();
+    manifest.install_directory = installed
+        .install_directory
+        .map(|directory| directory.into_os_string().into_string().unwrap());
+    manifest.args_file = installed
+        .args_file
+        .map(|file| file.to_string_lossy().replace('\\', "/"));
+
+    resolved.sha256 = Some(manifest.hash.clone());
+    Ok((manifest, resolved))
+}
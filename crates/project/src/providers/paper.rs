@@ -0,0 +1,209 @@
+use std::env;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+use super::{ResolvedServer, ServerProvider};
+use crate::installer::Downloader;
+use crate::Server;
+
+const PAPERMC_API_URL: &str = "https://api.papermc.io";
+
+#[derive(Deserialize, Debug)]
+struct ProjectResponse {
+    versions: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BuildsResponse {
+    builds: Vec<Build>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Build {
+    build: u32,
+    #[serde(default)]
+    channel: String,
+    downloads: Downloads,
+}
+
+#[derive(Deserialize, Debug)]
+struct Downloads {
+    application: Download,
+}
+
+#[derive(Deserialize, Debug)]
+struct Download {
+    name: String,
+    sha256: Option<String>,
+}
+
+/// Provider for PaperMC projects (paper, folia, velocity, waterfall)
+pub(super) struct PaperProvider {
+    project: String,
+    api_url: String,
+    downloader: Downloader,
+}
+
+impl PaperProvider {
+    /// The API base URL can be overridden with the `CRAFTY_PAPERMC_API_URL` variable
+    pub fn new(project: &str, downloader: Downloader) -> Self {
+        let api_url = env::var("CRAFTY_PAPERMC_API_URL").unwrap_or_else(|_| PAPERMC_API_URL.into());
+
+        Self {
+            project: project.to_string(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            downloader,
+        }
+    }
+}
+
+impl ServerProvider for PaperProvider {
+    /// Resolves the download URL of a build, if no build is provided,
+    /// the latest one for the given version will be used
+    async fn resolve(&self, version: &str, server: &Server) -> anyhow::Result<ResolvedServer> {
+        let project = &self.project;
+        let versions_url = format!(
+            "{}/v2/projects/{}/versions/{}",
+            self.api_url, project, version
+        );
+
+        let build = match server.build {
+            Some(build) => {
+                log::info!("Resolving {} {} build #{}...", project, version, build);
+                self.downloader
+                    .fetch_json::<Build>(&format!("{}/builds/{}", versions_url, build))
+                    .await?
+            }
+            None => {
+                log::info!("Resolving latest {} {} build...", project, version);
+                let mut builds = self
+                    .downloader
+                    .fetch_json::<BuildsResponse>(&format!("{}/builds", versions_url))
+                    .await?
+                    .builds;
+
+                // Prefer stable builds, but fallback to experimental ones if there is none
+                let latest_stable = builds.iter().rposition(|build| build.channel == "default");
+                match latest_stable {
+                    Some(index) => builds.swap_remove(index),
+                    None => builds.pop().ok_or_else(|| {
+                        anyhow!("No builds were found for {} {}", project, version)
+                    })?,
+                }
+            }
+        };
+
+        let url = format!(
+            "{}/builds/{}/downloads/{}",
+            versions_url, build.build, build.downloads.application.name
+        );
+
+        Ok(ResolvedServer {
+            url,
+            version: version.to_string(),
+            build: Some(build.build),
+            sha256: build.downloads.application.sha256,
+            ..Default::default()
+        })
+    }
+
+    /// The versions of a project are sorted from oldest to newest
+    async fn latest_version(&self) -> anyhow::Result<Option<String>> {
+        let project = self
+            .downloader
+            .fetch_json::<ProjectResponse>(&format!(
+                "{}/v2/projects/{}",
+                self.api_url, self.project
+            ))
+            .await?;
+
+        Ok(project.versions.into_iter().last())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(api_url: &str) -> PaperProvider {
+        PaperProvider {
+            project: "paper".into(),
+            api_url: api_url.into(),
+            downloader: Downloader::new(false),
+        }
+    }
+
+    fn server(yaml: &str) -> Server {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn resolves_the_latest_stable_build() {
+        let mut api = mockito::Server::new_async().await;
+        let builds = api
+            .mock("GET", "/v2/projects/paper/versions/1.20.4/builds")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"builds": [
+                    {"build": 1, "channel": "default", "downloads": {"application": {"name": "paper-1.20.4-1.jar", "sha256": "aa"}}},
+                    {"build": 2, "channel": "default", "downloads": {"application": {"name": "paper-1.20.4-2.jar", "sha256": "bb"}}},
+                    {"build": 3, "channel": "experimental", "downloads": {"application": {"name": "paper-1.20.4-3.jar"}}}
+                ]}"#,
+            )
+            .create_async()
+            .await;
+
+        let resolved = provider(&api.url())
+            .resolve("1.20.4", &server("version: 1.20.4"))
+            .await
+            .unwrap();
+
+        builds.assert_async().await;
+        assert_eq!(resolved.build, Some(2));
+        assert_eq!(resolved.sha256.as_deref(), Some("bb"));
+        assert_eq!(
+            resolved.url,
+            format!(
+                "{}/v2/projects/paper/versions/1.20.4/builds/2/downloads/paper-1.20.4-2.jar",
+                api.url()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_a_pinned_build() {
+        let mut api = mockito::Server::new_async().await;
+        api.mock("GET", "/v2/projects/paper/versions/1.20.4/builds/7")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"build": 7, "channel": "experimental", "downloads": {"application": {"name": "paper-1.20.4-7.jar"}}}"#,
+            )
+            .create_async()
+            .await;
+
+        let resolved = provider(&api.url())
+            .resolve("1.20.4", &server("version: 1.20.4\nbuild: 7"))
+            .await
+            .unwrap();
+
+        assert_eq!(resolved.build, Some(7));
+        assert_eq!(resolved.sha256, None);
+    }
+
+    #[tokio::test]
+    async fn fails_without_builds() {
+        let mut api = mockito::Server::new_async().await;
+        api.mock("GET", "/v2/projects/paper/versions/1.20.4/builds")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"builds": []}"#)
+            .create_async()
+            .await;
+
+        let result = provider(&api.url())
+            .resolve("1.20.4", &server("version: 1.20.4"))
+            .await;
+
+        assert!(result.is_err());
+    }
+}
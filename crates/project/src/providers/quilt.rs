@@ -0,0 +1,232 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+use super::{InstalledServer, ResolvedServer, ServerProvider};
+use crate::installer::{self, Downloader};
+use crate::Server;
+
+const QUILT_META_URL: &str = "https://meta.quiltmc.org";
+
+#[derive(Deserialize, Debug)]
+struct LoaderEntry {
+    loader: Component,
+}
+
+#[derive(Deserialize, Debug)]
+struct Component {
+    version: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GameVersion {
+    version: String,
+    #[serde(default)]
+    stable: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct InstallerEntry {
+    url: String,
+    version: String,
+}
+
+/// Provider for Quilt servers, Quilt does not provide a server launcher JAR,
+/// so the installer is downloaded and run to generate it
+pub(super) struct QuiltProvider {
+    meta_url: String,
+    downloader: Downloader,
+}
+
+impl QuiltProvider {
+    /// The meta API base URL can be overridden with the `CRAFTY_QUILT_META_URL` variable
+    pub fn new(downloader: Downloader) -> Self {
+        let meta_url = env::var("CRAFTY_QUILT_META_URL").unwrap_or_else(|_| QUILT_META_URL.into());
+
+        Self {
+            meta_url: meta_url.trim_end_matches('/').to_string(),
+            downloader,
+        }
+    }
+}
+
+impl ServerProvider for QuiltProvider {
+    /// Resolves the latest stable loader (unless pinned with `loader-version`)
+    /// and installer for the given version
+    async fn resolve(&self, version: &str, server: &Server) -> anyhow::Result<ResolvedServer> {
+        log::info!("Resolving Quilt {} server...", version);
+
+        let loaders = self
+            .downloader
+            .fetch_json::<Vec<LoaderEntry>>(&format!(
+                "{}/v3/versions/loader/{}",
+                self.meta_url, version
+            ))
+            .await?;
+
+        let loader_version = match server.loader_version.as_deref() {
+            Some(pinned) => loaders
+                .iter()
+                .find(|entry| entry.loader.version == pinned)
+                .map(|entry| entry.loader.version.clone()),
+            // Pre-release versions contain a suffix like `-beta.1`
+            None => loaders
+                .iter()
+                .find(|entry| !entry.loader.version.contains('-'))
+                .or(loaders.first())
+                .map(|entry| entry.loader.version.clone()),
+        }
+        .ok_or_else(|| {
+            anyhow!(
+                "Could not find Quilt loader \"{}\" for version \"{}\"",
+                server.loader_version.as_deref().unwrap_or("latest"),
+                version
+            )
+        })?;
+
+        let installer = self
+            .downloader
+            .fetch_json::<Vec<InstallerEntry>>(&format!("{}/v3/versions/installer", self.meta_url))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Could not find a Quilt installer version"))?;
+
+        Ok(ResolvedServer {
+            url: installer.url,
+            version: version.to_string(),
+            loader_version: Some(loader_version),
+            installer_version: Some(installer.version),
+            ..Default::default()
+        })
+    }
+
+    async fn latest_version(&self) -> anyhow::Result<Option<String>> {
+        let versions = self
+            .downloader
+            .fetch_json::<Vec<GameVersion>>(&format!("{}/v3/versions/game", self.meta_url))
+            .await?;
+
+        Ok(versions
+            .into_iter()
+            .find(|entry| entry.stable)
+            .map(|entry| entry.version))
+    }
+
+    async fn install(
+        &self,
+        resolved: &ResolvedServer,
+        downloaded_jar: PathBuf,
+        versions_directory: &Path,
+    ) -> anyhow::Result<InstalledServer> {
+        let loader_version = resolved.loader_version.as_deref().unwrap_or_default();
+        let install_directory =
+            versions_directory.join(format!("quilt-{}-{}", resolved.version, loader_version));
+
+        log::info!("Running Quilt installer, this might take a while...");
+        installer::run_installer(
+            &downloaded_jar,
+            &[
+                "install".into(),
+                "server".into(),
+                resolved.version.clone(),
+                loader_version.into(),
+                "--download-server".into(),
+                format!("--install-dir={}", install_directory.display()),
+            ],
+            versions_directory,
+        )
+        .await?;
+
+        Ok(InstalledServer {
+            jar_file: install_directory.join("quilt-server-launch.jar"),
+            install_directory: Some(install_directory),
+            args_file: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(meta_url: &str) -> QuiltProvider {
+        QuiltProvider {
+            meta_url: meta_url.into(),
+            downloader: Downloader::new(false),
+        }
+    }
+
+    fn server(yaml: &str) -> Server {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    async fn meta(loaders: &str) -> mockito::ServerGuard {
+        let mut meta = mockito::Server::new_async().await;
+        meta.mock("GET", "/v3/versions/loader/1.20.4")
+            .with_header("content-type", "application/json")
+            .with_body(loaders)
+            .create_async()
+            .await;
+        meta.mock("GET", "/v3/versions/installer")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"[
+                    {"url": "https://example.com/quilt-installer-0.9.2.jar", "version": "0.9.2"},
+                    {"url": "https://example.com/quilt-installer-0.9.1.jar", "version": "0.9.1"}
+                ]"#,
+            )
+            .create_async()
+            .await;
+        meta
+    }
+
+    #[tokio::test]
+    async fn resolves_the_latest_stable_loader_and_installer() {
+        let meta = meta(
+            r#"[
+                {"loader": {"version": "0.26.0-beta.1"}},
+                {"loader": {"version": "0.25.0"}},
+                {"loader": {"version": "0.24.0"}}
+            ]"#,
+        )
+        .await;
+        let provider = provider(&meta.url());
+
+        let resolved = provider
+            .resolve("1.20.4", &server("version: 1.20.4"))
+            .await
+            .unwrap();
+        assert_eq!(resolved.loader_version.as_deref(), Some("0.25.0"));
+        assert_eq!(resolved.installer_version.as_deref(), Some("0.9.2"));
+        assert_eq!(
+            resolved.url,
+            "https://example.com/quilt-installer-0.9.2.jar"
+        );
+
+        let pinned = provider
+            .resolve("1.20.4", &server("version: 1.20.4\nloader-version: 0.24.0"))
+            .await
+            .unwrap();
+        assert_eq!(pinned.loader_version.as_deref(), Some("0.24.0"));
+
+        let missing = provider
+            .resolve("1.20.4", &server("version: 1.20.4\nloader-version: 0.1.0"))
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_pre_release_loaders() {
+        let meta = meta(r#"[{"loader": {"version": "0.26.0-beta.2"}}, {"loader": {"version": "0.26.0-beta.1"}}]"#)
+            .await;
+
+        let resolved = provider(&meta.url())
+            .resolve("1.20.4", &server("version: 1.20.4"))
+            .await
+            .unwrap();
+        assert_eq!(resolved.loader_version.as_deref(), Some("0.26.0-beta.2"));
+    }
+}
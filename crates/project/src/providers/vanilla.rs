@@ -0,0 +1,178 @@
+use std::env;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+use super::{ResolvedServer, ServerProvider};
+use crate::installer::Downloader;
+use crate::Server;
+
+const VERSION_MANIFEST_URL: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+#[derive(Deserialize, Debug)]
+struct VersionManifest {
+    latest: LatestVersions,
+    versions: Vec<VersionEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LatestVersions {
+    release: String,
+    snapshot: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct VersionEntry {
+    id: String,
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct VersionDetails {
+    downloads: VersionDownloads,
+}
+
+#[derive(Deserialize, Debug)]
+struct VersionDownloads {
+    server: Option<Download>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Download {
+    sha1: String,
+    url: String,
+}
+
+/// Provider for vanilla servers, resolved through Mojang's version manifest
+pub(super) struct VanillaProvider {
+    manifest_url: String,
+    downloader: Downloader,
+}
+
+impl VanillaProvider {
+    /// The manifest URL can be overridden with the `CRAFTY_MOJANG_MANIFEST_URL` variable
+    pub fn new(downloader: Downloader) -> Self {
+        Self {
+            manifest_url: env::var("CRAFTY_MOJANG_MANIFEST_URL")
+                .unwrap_or_else(|_| VERSION_MANIFEST_URL.into()),
+            downloader,
+        }
+    }
+}
+
+impl ServerProvider for VanillaProvider {
+    /// Resolves the server download of a version,
+    /// `latest-release` and `latest-snapshot` can be used instead of a version
+    async fn resolve(&self, version: &str, _server: &Server) -> anyhow::Result<ResolvedServer> {
+        log::info!("Resolving vanilla {} server...", version);
+        let manifest = self
+            .downloader
+            .fetch_json::<VersionManifest>(&self.manifest_url)
+            .await?;
+
+        let version = match version {
+            "latest-release" => &manifest.latest.release,
+            "latest-snapshot" => &manifest.latest.snapshot,
+            version => version,
+        };
+
+        let entry = manifest
+            .versions
+            .iter()
+            .find(|entry| entry.id == version)
+            .ok_or_else(|| anyhow!("Could not find Minecraft version \"{}\"", version))?;
+
+        let details = self
+            .downloader
+            .fetch_json::<VersionDetails>(&entry.url)
+            .await?;
+        let download = details.downloads.server.ok_or_else(|| {
+            anyhow!(
+                "Minecraft version \"{}\" does not have a server download",
+                version
+            )
+        })?;
+
+        Ok(ResolvedServer {
+            url: download.url,
+            version: version.to_string(),
+            sha1: Some(download.sha1),
+            ..Default::default()
+        })
+    }
+
+    async fn latest_version(&self) -> anyhow::Result<Option<String>> {
+        let manifest = self
+            .downloader
+            .fetch_json::<VersionManifest>(&self.manifest_url)
+            .await?;
+        Ok(Some(manifest.latest.release))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn mock_manifest(api: &mut mockito::Server) {
+        let manifest = format!(
+            r#"{{
+                "latest": {{"release": "1.20.4", "snapshot": "24w03a"}},
+                "versions": [
+                    {{"id": "24w03a", "url": "{url}/24w03a.json"}},
+                    {{"id": "1.20.4", "url": "{url}/1.20.4.json"}},
+                    {{"id": "a1.0.4", "url": "{url}/a1.0.4.json"}}
+                ]
+            }}"#,
+            url = api.url()
+        );
+        api.mock("GET", "/manifest.json")
+            .with_body(manifest)
+            .create_async()
+            .await;
+        api.mock("GET", "/1.20.4.json")
+            .with_body(r#"{"downloads": {"server": {"sha1": "abc", "url": "https://example.com/server.jar"}}}"#)
+            .create_async()
+            .await;
+        api.mock("GET", "/a1.0.4.json")
+            .with_body(r#"{"downloads": {}}"#)
+            .create_async()
+            .await;
+    }
+
+    fn provider(api: &mockito::Server) -> VanillaProvider {
+        VanillaProvider {
+            manifest_url: format!("{}/manifest.json", api.url()),
+            downloader: Downloader::new(false),
+        }
+    }
+
+    fn server() -> Server {
+        serde_yaml::from_str("brand: vanilla").unwrap()
+    }
+
+    #[tokio::test]
+    async fn resolves_latest_release() {
+        let mut api = mockito::Server::new_async().await;
+        mock_manifest(&mut api).await;
+
+        let resolved = provider(&api)
+            .resolve("latest-release", &server())
+            .await
+            .unwrap();
+
+        assert_eq!(resolved.version, "1.20.4");
+        assert_eq!(resolved.url, "https://example.com/server.jar");
+        assert_eq!(resolved.sha1.as_deref(), Some("abc"));
+    }
+
+    #[tokio::test]
+    async fn fails_for_unknown_versions_and_missing_servers() {
+        let mut api = mockito::Server::new_async().await;
+        mock_manifest(&mut api).await;
+
+        assert!(provider(&api).resolve("1.0.0", &server()).await.is_err());
+        assert!(provider(&api).resolve("a1.0.4", &server()).await.is_err());
+    }
+}
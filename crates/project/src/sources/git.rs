@@ -0,0 +1,374 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use anyhow::{anyhow, Context};
+use glob::{MatchOptions, Pattern};
+use tokio::process::Command;
+
+use crate::dependencies::ResolvedDependency;
+use crate::installer::Downloader;
+
+const PREFIX: &str = "git+";
+
+/// Whether the source is a git repository (eg. `git+https://github.com/owner/repo.git#main`)
+pub(crate) fn is_git_source(source: &str) -> bool {
+    source.starts_with(PREFIX)
+}
+
+/// Splits a `git+<url>#<ref>` source into the repository URL and the ref, if any
+fn parse(source: &str) -> (&str, Option<&str>) {
+    let repository = source.strip_prefix(PREFIX).unwrap_or(source);
+    match repository.split_once('#') {
+        Some((url, reference)) => (
+            url,
+            Some(reference).filter(|reference| !reference.is_empty()),
+        ),
+        None => (repository, None),
+    }
+}
+
+/// Clones (or fetches) the repository in a directory, checks out the ref (or the locked commit),
+/// runs the build command and returns the artifact matching the pattern, its version is the commit.
+/// The build is skipped if the commit was the last one built and the artifact is still there
+pub(crate) async fn resolve(
+    downloader: &Downloader,
+    source: &str,
+    locked_commit: Option<&str>,
+    built_commit: Option<&str>,
+    build: Option<&str>,
+    artifact: Option<&str>,
+    repository_directory: &Path,
+) -> anyhow::Result<ResolvedDependency> {
+    let (url, reference) = parse(source);
+    let artifact =
+        artifact.ok_or_else(|| anyhow!("The `artifact` pattern is required for git sources"))?;
+
+    if !repository_directory.join(".git").exists() {
+        check_online(downloader, url)?;
+        log::info!("Cloning \"{}\"...", url);
+        let parent = repository_directory.parent().unwrap();
+        fs::create_dir_all(parent)?;
+        git(
+            parent,
+            // URLs starting with `-` would otherwise be read as options (eg. `--upload-pack=<command>`)
+            &[
+                "clone",
+                "--no-checkout",
+                "--",
+                url,
+                &path_arg(repository_directory),
+            ],
+        )
+        .await?;
+    } else {
+        let has_locked_commit = match locked_commit {
+            Some(commit) => has_commit(repository_directory, commit).await,
+            None => false,
+        };
+
+        if !has_locked_commit {
+            check_online(downloader, url)?;
+            log::info!("Fetching \"{}\"...", url);
+            git(
+                repository_directory,
+                &["remote", "set-url", "--", "origin", url],
+            )
+            .await?;
+            git(
+                repository_directory,
+                &["fetch", "--tags", "--force", "--prune", "origin"],
+            )
+            .await?;
+        }
+    }
+
+    let commit = match (locked_commit, reference) {
+        (Some(commit), _) => commit.to_string(),
+        // Branches are taken from the remote, as the local ones are never updated
+        (None, Some(reference)) => {
+            match rev_parse(repository_directory, &format!("origin/{}", reference)).await {
+                Ok(commit) => commit,
+                Err(_) => rev_parse(repository_directory, reference)
+                    .await
+                    .map_err(|_| anyhow!("Could not find \"{}\" in \"{}\"", reference, url))?,
+            }
+        }
+        (None, None) => rev_parse(repository_directory, "origin/HEAD").await?,
+    };
+    git(
+        repository_directory,
+        &["checkout", "--quiet", "--force", "--detach", &commit],
+    )
+    .await?;
+
+    let is_built = built_commit == Some(commit.as_str())
+        && find_artifact(repository_directory, artifact).is_ok();
+    match build {
+        Some(_) if is_built => log::debug!("Commit {} of \"{}\" is already built", commit, url),
+        Some(command) => run_build(repository_directory, command).await?,
+        None => {}
+    }
+
+    let artifact_path = find_artifact(repository_directory, artifact)?;
+    Ok(ResolvedDependency {
+        url: artifact_path.to_string_lossy().to_string(),
+        version: Some(commit),
+        ..Default::default()
+    })
+}
+
+/// Local repositories can be cloned without network requests
+fn check_online(downloader: &Downloader, url: &str) -> anyhow::Result<()> {
+    if url.starts_with("file://") {
+        return Ok(());
+    }
+
+    downloader.check_online(url)
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+async fn has_commit(repository_directory: &Path, commit: &str) -> bool {
+    git(
+        repository_directory,
+        &["cat-file", "-e", &format!("{}^{{commit}}", commit)],
+    )
+    .await
+    .is_ok()
+}
+
+async fn rev_parse(repository_directory: &Path, revision: &str) -> anyhow::Result<String> {
+    git(
+        repository_directory,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", revision),
+        ],
+    )
+    .await
+}
+
+/// Runs a git command and returns its output
+async fn git(working_directory: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .current_dir(working_directory)
+        .args(args)
+        // Fail instead of waiting for credentials
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("Could not run git, make sure it is installed")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`git {}` failed ({}):\n{}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Runs the build command with the shell of the platform, in the repository directory
+async fn run_build(repository_directory: &Path, command: &str) -> anyhow::Result<()> {
+    log::info!("Running `{}`...", command);
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    let output = shell
+        .arg(command)
+        .current_dir(repository_directory)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("Could not run the build command `{}`", command))?;
+
+    log::debug!("{}", String::from_utf8_lossy(&output.stdout));
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "The build command `{}` failed ({}):\n{}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(())
+}
+
+/// Finds the file matching the pattern (eg. `build/libs/*-all.jar`) in the repository,
+/// the most recently modified one is used if several match (eg. artifacts of previous versions)
+fn find_artifact(repository_directory: &Path, pattern: &str) -> anyhow::Result<PathBuf> {
+    let full_pattern = format!(
+        "{}/{}",
+        Pattern::escape(&repository_directory.to_string_lossy()),
+        pattern
+    );
+    let options = MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+
+    glob::glob_with(&full_pattern, options)
+        .with_context(|| format!("Invalid artifact pattern \"{}\"", pattern))?
+        .flatten()
+        .filter(|path| path.is_file())
+        .max_by_key(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "No file matching \"{}\" was found in \"{}\", check the `artifact` pattern and the `build` command",
+                pattern,
+                repository_directory.display()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sources() {
+        assert_eq!(
+            parse("git+https://github.com/owner/repo.git#main"),
+            ("https://github.com/owner/repo.git", Some("main"))
+        );
+        assert_eq!(
+            parse("git+file:///home/me/repo#"),
+            ("file:///home/me/repo", None)
+        );
+        assert_eq!(
+            parse("git+https://example.com/repo"),
+            ("https://example.com/repo", None)
+        );
+    }
+
+    async fn commit(repository: &Path, file: &str, contents: &str) -> String {
+        fs::write(repository.join(file), contents).unwrap();
+        git(repository, &["add", "-A"]).await.unwrap();
+        git(
+            repository,
+            &[
+                "-c",
+                "user.name=Test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                file,
+            ],
+        )
+        .await
+        .unwrap();
+        rev_parse(repository, "HEAD").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn does_not_read_urls_as_options() {
+        let directory = tempfile::tempdir().unwrap();
+        let marker = directory.path().join("marker");
+        let source = format!("git+--upload-pack=touch {}", marker.display());
+
+        let result = resolve(
+            &Downloader::new(false),
+            &source,
+            None,
+            None,
+            None,
+            Some("*.jar"),
+            &directory.path().join("src"),
+        )
+        .await;
+
+        // The URL is passed to git as the repository to clone
+        let err = format!("{:#}", result.unwrap_err());
+        assert!(err.contains("repository '--upload-pack="), "{}", err);
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn builds_the_locked_commit() {
+        let directory = tempfile::tempdir().unwrap();
+        let remote = directory.path().join("remote");
+        fs::create_dir(&remote).unwrap();
+        git(&remote, &["init", "--quiet"]).await.unwrap();
+        let first = commit(&remote, "plugin.txt", "1").await;
+        let second = commit(&remote, "plugin.txt", "2").await;
+
+        let source = format!("git+file://{}", remote.display());
+        let build = "mkdir -p out && cp plugin.txt out/plugin.jar";
+        let checkout = directory.path().join("src");
+        let downloader = Downloader::new(true);
+
+        let latest = resolve(
+            &downloader,
+            &source,
+            None,
+            None,
+            Some(build),
+            Some("out/*.jar"),
+            &checkout,
+        )
+        .await
+        .unwrap();
+        assert_eq!(latest.version.as_deref(), Some(second.as_str()));
+        assert_eq!(fs::read_to_string(&latest.url).unwrap(), "2");
+
+        let locked = resolve(
+            &downloader,
+            &source,
+            Some(&first),
+            Some(&second),
+            Some(build),
+            Some("out/*.jar"),
+            &checkout,
+        )
+        .await
+        .unwrap();
+        assert_eq!(locked.version.as_deref(), Some(first.as_str()));
+        assert_eq!(fs::read_to_string(&locked.url).unwrap(), "1");
+
+        // Already built commits are not built again
+        let built = resolve(
+            &downloader,
+            &source,
+            Some(&first),
+            Some(&first),
+            Some("exit 1"),
+            Some("out/*.jar"),
+            &checkout,
+        )
+        .await;
+        assert!(built.is_ok());
+
+        let missing = resolve(
+            &downloader,
+            &source,
+            None,
+            None,
+            None,
+            Some("*.zip"),
+            &checkout,
+        )
+        .await;
+        assert!(missing.is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use std::env;
+
+use anyhow::anyhow;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::dependencies::ResolvedDependency;
+use crate::installer::{self, Downloader};
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Asset {
+    name: String,
+    /// API URL of the asset, it also works for private repositories
+    url: String,
+}
+
+/// Base URL of the GitHub API, can be overridden with the `CRAFTY_GITHUB_API_URL` variable
+fn api_url() -> String {
+    env::var("CRAFTY_GITHUB_API_URL")
+        .unwrap_or_else(|_| GITHUB_API_URL.into())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Headers for requests to the GitHub API, the `GITHUB_TOKEN` variable is used to authenticate if set
+pub(crate) fn request_headers(url: &str) -> HeaderMap {
+    api_headers(url, &api_url(), env::var("GITHUB_TOKEN").ok().as_deref())
+}
+
+fn api_headers(url: &str, api_url: &str, token: Option<&str>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    // The token is only sent to the API itself, not to look-alike hosts or redirect targets
+    let is_api_url = match (Url::parse(url), Url::parse(api_url)) {
+        (Ok(url), Ok(api_url)) => installer::is_under_prefix(&url, &api_url),
+        _ => false,
+    };
+    if !is_api_url {
+        return headers;
+    }
+
+    if let Some(token) = token {
+        if let Ok(mut value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+    }
+
+    // Asset URLs return the asset details instead of the file unless requested
+    let accept = if url.contains("/releases/assets/") {
+        "application/octet-stream"
+    } else {
+        "application/vnd.github+json"
+    };
+    headers.insert(ACCEPT, HeaderValue::from_static(accept));
+
+    headers
+}
+
+/// Resolves a release asset of a `<owner>/<repo>` repository,
+/// `latest` can be used to resolve the latest release, otherwise the version is the release tag
+pub(crate) async fn resolve(
+    downloader: &Downloader,
+    repository: &str,
+    version: &str,
+    asset: Option<&str>,
+) -> anyhow::Result<ResolvedDependency> {
+    if repository.split('/').count() != 2 {
+        return Err(anyhow!(
+            "Invalid GitHub repository \"{}\", expected <owner>/<repo>",
+            repository
+        ));
+    }
+
+    log::info!("Resolving \"{}\" {} from GitHub...", repository, version);
+    let url = match version {
+        "latest" => format!("{}/repos/{}/releases/latest", api_url(), repository),
+        tag => format!("{}/repos/{}/releases/tags/{}", api_url(), repository, tag),
+    };
+    let release = downloader.fetch_json::<Release>(&url).await?;
+
+    let asset = match asset {
+        Some(pattern) => {
+            let mut found = None;
+            for asset in release.assets {
+                if super::matches_pattern(pattern, &asset.name)? {
+                    found = Some(asset);
+                    break;
+                }
+            }
+            found
+        }
+        None => {
+            let mut assets = release.assets;
+            let index = assets
+                .iter()
+                .position(|asset| asset.name.ends_with(".jar"))
+                .unwrap_or(0);
+            (!assets.is_empty()).then(|| assets.swap_remove(index))
+        }
+    }
+    .ok_or_else(|| {
+        anyhow!(
+            "Could not find a matching asset in release \"{}\" of \"{}\"",
+            release.tag_name,
+            repository
+        )
+    })?;
+
+    Ok(ResolvedDependency {
+        url: asset.url,
+        file_name: Some(asset.name),
+        version: Some(release.tag_name),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_authenticates_requests_to_the_api() {
+        let request_headers = |url| api_headers(url, GITHUB_API_URL, Some("secret"));
+
+        let headers = request_headers("https://api.github.com/repos/owner/repo/releases/latest");
+        assert_eq!(headers[AUTHORIZATION], "Bearer secret");
+        let headers = request_headers("https://api.github.com/repos/owner/repo/releases/assets/1");
+        assert_eq!(headers[ACCEPT], "application/octet-stream");
+
+        for url in [
+            "https://api.github.com.evil.example/repos/owner/repo/releases/latest",
+            "http://api.github.com/repos/owner/repo/releases/latest",
+            "https://objects.githubusercontent.com/github-production-release-asset/1",
+        ] {
+            let headers = request_headers(url);
+            assert!(headers.get(AUTHORIZATION).is_none(), "{}", url);
+        }
+    }
+}
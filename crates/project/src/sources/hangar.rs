@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::env;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+use crate::dependencies::ResolvedDependency;
+use crate::installer::Downloader;
+use crate::Server;
+
+const HANGAR_API_URL: &str = "https://hangar.papermc.io/api/v1";
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Version {
+    name: String,
+    downloads: HashMap<String, Download>,
+    /// Versions of each platform the version supports
+    #[serde(default)]
+    platform_dependencies: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct VersionsResponse {
+    result: Vec<Version>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Download {
+    file_info: Option<FileInfo>,
+    external_url: Option<String>,
+    download_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FileInfo {
+    name: String,
+    sha256_hash: Option<String>,
+}
+
+/// Base URL of the Hangar API, can be overridden with the `CRAFTY_HANGAR_API_URL` variable
+fn api_url() -> String {
+    env::var("CRAFTY_HANGAR_API_URL")
+        .unwrap_or_else(|_| HANGAR_API_URL.into())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Hangar platform matching a server brand
+fn platform(server: &Server) -> anyhow::Result<&'static str> {
+    let brand = server.brand.as_deref().unwrap_or_default().to_lowercase();
+    match brand.as_str() {
+        "paper" | "purpur" | "folia" | "spigot" | "bukkit" => Ok("PAPER"),
+        "velocity" => Ok("VELOCITY"),
+        "waterfall" | "bungeecord" => Ok("WATERFALL"),
+        _ => Err(anyhow!(
+            "Hangar dependencies are not supported for the server brand \"{}\"",
+            brand
+        )),
+    }
+}
+
+/// Resolves a version of a Hangar project from a `<owner>/<slug>` identifier,
+/// `latest` can be used to resolve the latest release for the platform of the server
+pub(crate) async fn resolve(
+    downloader: &Downloader,
+    project: &str,
+    version: &str,
+    server: &Server,
+) -> anyhow::Result<ResolvedDependency> {
+    resolve_from(downloader, &api_url(), project, version, server).await
+}
+
+async fn resolve_from(
+    downloader: &Downloader,
+    api_url: &str,
+    project: &str,
+    version: &str,
+    server: &Server,
+) -> anyhow::Result<ResolvedDependency> {
+    let (_owner, slug) = project.split_once('/').ok_or_else(|| {
+        anyhow!(
+            "Invalid Hangar project \"{}\", expected <owner>/<slug>",
+            project
+        )
+    })?;
+    let platform = platform(server)?;
+
+    let found = match version {
+        "latest" => {
+            log::info!("Resolving latest release of \"{}\" from Hangar...", project);
+            latest_release(downloader, api_url, slug, platform)
+                .await?
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No release of \"{}\" was found for the {} platform",
+                        project,
+                        platform
+                    )
+                })?
+        }
+        version => {
+            log::info!("Resolving \"{}\" {} from Hangar...", project, version);
+            downloader
+                .fetch_json::<Version>(&format!(
+                    "{}/projects/{}/versions/{}",
+                    api_url, slug, version
+                ))
+                .await?
+        }
+    };
+    let version = &found.name;
+
+    let download = found.downloads.get(platform).ok_or_else(|| {
+        anyhow!(
+            "Version \"{}\" of \"{}\" has no download for the {} platform",
+            version,
+            project,
+            platform
+        )
+    })?;
+
+    let url = download
+        .download_url
+        .clone()
+        .or(download.external_url.clone())
+        .ok_or_else(|| {
+            anyhow!(
+                "Version \"{}\" of \"{}\" has no download URL",
+                version,
+                project
+            )
+        })?;
+
+    Ok(ResolvedDependency {
+        url,
+        file_name: download.file_info.as_ref().map(|info| info.name.clone()),
+        version: Some(found.name),
+        sha256: download
+            .file_info
+            .as_ref()
+            .and_then(|info| info.sha256_hash.clone()),
+        ..Default::default()
+    })
+}
+
+/// Latest release of a project supporting a platform, the versions are listed from newest to oldest.
+/// The `/latestrelease` endpoint is not used, as it ignores the platforms
+async fn latest_release(
+    downloader: &Downloader,
+    api_url: &str,
+    slug: &str,
+    platform: &str,
+) -> anyhow::Result<Option<Version>> {
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}/projects/{}/versions", api_url, slug),
+        &[
+            ("limit", "25"),
+            ("channel", "Release"),
+            ("platform", platform),
+        ],
+    )?;
+
+    let versions = downloader
+        .fetch_json::<VersionsResponse>(url.as_str())
+        .await?;
+    Ok(versions
+        .result
+        .into_iter()
+        .find(|version| version.platform_dependencies.contains_key(platform)))
+}
+
+/// Latest release of a Hangar project that supports the server version, if any
+pub(crate) async fn latest_compatible(
+    downloader: &Downloader,
+    project: &str,
+    server: &Server,
+) -> anyhow::Result<Option<String>> {
+    let slug = project.split_once('/').map_or(project, |(_, slug)| slug);
+    let platform = platform(server)?;
+
+    let mut params = vec![
+        ("limit", "1"),
+        ("channel", "Release"),
+        ("platform", platform),
+    ];
+    if let Some(version) = server.version.as_deref() {
+        params.push(("platformVersion", version));
+    }
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}/projects/{}/versions", api_url(), slug),
+        &params,
+    )?;
+
+    let versions = downloader
+        .fetch_json::<VersionsResponse>(url.as_str())
+        .await?;
+    Ok(versions
+        .result
+        .into_iter()
+        .next()
+        .map(|version| version.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(yaml: &str) -> Server {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    const VERSIONS: &str = r#"{"result": [
+        {"name": "3.0.0", "platformDependencies": {"VELOCITY": ["3.3"]}, "downloads": {
+            "VELOCITY": {"fileInfo": {"name": "plugin-velocity.jar", "sha256Hash": "aa"}, "downloadUrl": "https://cdn/velocity.jar"}
+        }},
+        {"name": "2.9.0", "platformDependencies": {"PAPER": ["1.20.4"], "VELOCITY": ["3.3"]}, "downloads": {
+            "PAPER": {"fileInfo": {"name": "plugin-paper.jar", "sha256Hash": "bb"}, "downloadUrl": "https://cdn/paper.jar"},
+            "VELOCITY": {"externalUrl": "https://example.com/velocity.jar"}
+        }}
+    ]}"#;
+
+    #[tokio::test]
+    async fn resolves_the_latest_release_for_the_platform() {
+        let mut api = mockito::Server::new_async().await;
+        api.mock("GET", "/projects/Plugin/versions")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("channel".into(), "Release".into()),
+                mockito::Matcher::UrlEncoded("platform".into(), "PAPER".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(VERSIONS)
+            .create_async()
+            .await;
+        let downloader = Downloader::new(false);
+
+        let resolved = resolve_from(
+            &downloader,
+            &api.url(),
+            "Owner/Plugin",
+            "latest",
+            &server("brand: paper\nversion: 1.20.4"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(resolved.version.as_deref(), Some("2.9.0"));
+        assert_eq!(resolved.url, "https://cdn/paper.jar");
+        assert_eq!(resolved.file_name.as_deref(), Some("plugin-paper.jar"));
+        assert_eq!(resolved.sha256.as_deref(), Some("bb"));
+    }
+
+    #[tokio::test]
+    async fn resolves_pinned_versions() {
+        let mut api = mockito::Server::new_async().await;
+        api.mock("GET", "/projects/Plugin/versions/2.9.0")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"name": "2.9.0", "downloads": {
+                    "VELOCITY": {"externalUrl": "https://example.com/velocity.jar"}
+                }}"#,
+            )
+            .create_async()
+            .await;
+        let downloader = Downloader::new(false);
+        let resolve = |brand| {
+            let api_url = api.url();
+            let downloader = &downloader;
+            async move {
+                resolve_from(
+                    downloader,
+                    &api_url,
+                    "Owner/Plugin",
+                    "2.9.0",
+                    &server(&format!("brand: {}", brand)),
+                )
+                .await
+            }
+        };
+
+        let velocity = resolve("velocity").await.unwrap();
+        assert_eq!(velocity.url, "https://example.com/velocity.jar");
+        assert_eq!(velocity.sha256, None);
+
+        assert!(resolve("paper").await.is_err());
+        assert!(resolve("fabric").await.is_err());
+    }
+}
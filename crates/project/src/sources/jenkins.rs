@@ -0,0 +1,140 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+
+use crate::dependencies::ResolvedDependency;
+use crate::installer::Downloader;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Build {
+    number: u32,
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Artifact {
+    file_name: String,
+    relative_path: String,
+}
+
+/// Resolves an artifact of a Jenkins job from its URL (eg. `https://ci.lucko.me/job/spark`),
+/// `latest` can be used to resolve the last successful build, otherwise the version is the build number
+pub(crate) async fn resolve(
+    downloader: &Downloader,
+    job_url: &str,
+    version: &str,
+    artifact: Option<&str>,
+) -> anyhow::Result<ResolvedDependency> {
+    let job_url = job_url.trim_end_matches('/');
+    let build = match version {
+        "latest" => "lastSuccessfulBuild",
+        build => build,
+    };
+
+    log::info!(
+        "Resolving build {} of \"{}\" from Jenkins...",
+        build,
+        job_url
+    );
+    let build = downloader
+        .fetch_json::<Build>(&format!("{}/{}/api/json", job_url, build))
+        .await?;
+
+    let mut found = None;
+    for candidate in build.artifacts {
+        let matches = match artifact {
+            Some(pattern) => super::matches_pattern(pattern, &candidate.file_name)?,
+            None => candidate.file_name.ends_with(".jar"),
+        };
+
+        if matches {
+            found = Some(candidate);
+            break;
+        }
+    }
+    let artifact = found.ok_or_else(|| {
+        anyhow!(
+            "Could not find a matching artifact in build #{} of \"{}\"",
+            build.number,
+            job_url
+        )
+    })?;
+
+    Ok(ResolvedDependency {
+        url: format!(
+            "{}/{}/artifact/{}",
+            job_url, build.number, artifact.relative_path
+        ),
+        file_name: Some(artifact.file_name),
+        version: Some(build.number.to_string()),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILD: &str = r#"{"number": 384, "artifacts": [
+        {"fileName": "spark-1.10.43-api.txt", "relativePath": "spark-api/build/spark-1.10.43-api.txt"},
+        {"fileName": "spark-1.10.43-bukkit.jar", "relativePath": "spark-bukkit/build/libs/spark-1.10.43-bukkit.jar"},
+        {"fileName": "spark-1.10.43-velocity.jar", "relativePath": "spark-velocity/build/libs/spark-1.10.43-velocity.jar"}
+    ]}"#;
+
+    async fn jenkins(build: &str) -> mockito::ServerGuard {
+        let mut jenkins = mockito::Server::new_async().await;
+        jenkins
+            .mock("GET", format!("/job/spark/{}/api/json", build).as_str())
+            .with_header("content-type", "application/json")
+            .with_body(BUILD)
+            .create_async()
+            .await;
+        jenkins
+    }
+
+    #[tokio::test]
+    async fn resolves_the_last_successful_build() {
+        let jenkins = jenkins("lastSuccessfulBuild").await;
+        let job_url = format!("{}/job/spark/", jenkins.url());
+
+        let resolved = resolve(&Downloader::new(false), &job_url, "latest", None)
+            .await
+            .unwrap();
+
+        // The first JAR is used without a pattern
+        assert_eq!(resolved.version.as_deref(), Some("384"));
+        assert_eq!(
+            resolved.file_name.as_deref(),
+            Some("spark-1.10.43-bukkit.jar")
+        );
+        assert_eq!(
+            resolved.url,
+            format!(
+                "{}/job/spark/384/artifact/spark-bukkit/build/libs/spark-1.10.43-bukkit.jar",
+                jenkins.url()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_matching_artifacts_of_pinned_builds() {
+        let jenkins = jenkins("384").await;
+        let job_url = format!("{}/job/spark", jenkins.url());
+        let downloader = Downloader::new(false);
+
+        let resolved = resolve(&downloader, &job_url, "384", Some("*-velocity.jar"))
+            .await
+            .unwrap();
+        assert_eq!(
+            resolved.file_name.as_deref(),
+            Some("spark-1.10.43-velocity.jar")
+        );
+
+        let missing = resolve(&downloader, &job_url, "384", Some("*-fabric.jar")).await;
+        assert!(missing.is_err());
+        let unknown = resolve(&downloader, &job_url, "383", None).await;
+        assert!(unknown.is_err());
+    }
+}
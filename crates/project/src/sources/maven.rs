@@ -0,0 +1,417 @@
+use anyhow::anyhow;
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::Deserialize;
+
+use crate::dependencies::{Repository, ResolvedDependency};
+use crate::installer::Downloader;
+
+const MAVEN_CENTRAL_URL: &str = "https://repo.maven.apache.org/maven2";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct Metadata {
+    #[serde(default)]
+    versioning: Versioning,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct Versioning {
+    latest: Option<String>,
+    release: Option<String>,
+    snapshot: Option<Snapshot>,
+    snapshot_versions: Option<SnapshotVersions>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot {
+    timestamp: Option<String>,
+    build_number: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotVersions {
+    #[serde(default)]
+    snapshot_version: Vec<SnapshotVersion>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotVersion {
+    classifier: Option<String>,
+    extension: String,
+    value: String,
+}
+
+/// Maven coordinates in the `<group>:<artifact>[:<version>[:classifier]]` format
+struct Coordinates<'a> {
+    group: &'a str,
+    artifact: &'a str,
+    version: &'a str,
+    classifier: Option<&'a str>,
+}
+
+impl<'a> Coordinates<'a> {
+    /// Parses the coordinates, the version defaults to the given one if not included
+    fn parse(coordinates: &'a str, version: &'a str) -> anyhow::Result<Self> {
+        let parts: Vec<&str> = coordinates.split(':').collect();
+        match parts.as_slice() {
+            [group, artifact] => Ok(Self {
+                group,
+                artifact,
+                version,
+                classifier: None,
+            }),
+            [group, artifact, version] => Ok(Self {
+                group,
+                artifact,
+                version,
+                classifier: None,
+            }),
+            [group, artifact, version, classifier] => Ok(Self {
+                group,
+                artifact,
+                version,
+                classifier: Some(classifier),
+            }),
+            _ => Err(anyhow!(
+                "Invalid Maven coordinates \"{}\", expected <group>:<artifact>:<version>[:classifier]",
+                coordinates
+            )),
+        }
+    }
+
+    fn artifact_url(&self, repository: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            repository,
+            self.group.replace('.', "/"),
+            self.artifact
+        )
+    }
+}
+
+/// Registers the credentials of the repositories, so they are used when requesting their URLs
+pub(crate) fn register_credentials(
+    downloader: &mut Downloader,
+    repositories: &[Repository],
+) -> anyhow::Result<()> {
+    for repository in repositories {
+        let (Some(username), Some(password)) = (&repository.username, &repository.password) else {
+            continue;
+        };
+
+        let credentials = format!(
+            "{}:{}",
+            crate::interpolate_env(username)?,
+            crate::interpolate_env(password)?
+        );
+        let mut value = HeaderValue::from_str(&format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(credentials)
+        ))?;
+        value.set_sensitive(true);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, value);
+        downloader.register_headers(&repository.url, headers)?;
+    }
+
+    Ok(())
+}
+
+/// Resolves an artifact from the first repository that has it,
+/// the version can be a specific version, a `-SNAPSHOT` version, `LATEST` or `RELEASE`
+pub(crate) async fn resolve(
+    downloader: &Downloader,
+    coordinates: &str,
+    version: &str,
+    repositories: &[Repository],
+) -> anyhow::Result<ResolvedDependency> {
+    let version = match version {
+        "latest" => "RELEASE",
+        version => version,
+    };
+    let coordinates = Coordinates::parse(coordinates, version)?;
+
+    let repositories: Vec<String> = if repositories.is_empty() {
+        vec![MAVEN_CENTRAL_URL.to_string()]
+    } else {
+        repositories
+            .iter()
+            .map(|repository| repository.url.trim_end_matches('/').to_string())
+            .collect()
+    };
+
+    let mut errors = vec![];
+    for repository in &repositories {
+        match resolve_from(downloader, &coordinates, repository).await {
+            Ok(resolved) => return Ok(resolved),
+            Err(err) => errors.push(format!("{}: {}", repository, err)),
+        }
+    }
+
+    Err(anyhow!(
+        "Could not resolve \"{}:{}:{}\" from any repository\n{}",
+        coordinates.group,
+        coordinates.artifact,
+        coordinates.version,
+        errors.join("\n")
+    ))
+}
+
+async fn resolve_from(
+    downloader: &Downloader,
+    coordinates: &Coordinates<'_>,
+    repository: &str,
+) -> anyhow::Result<ResolvedDependency> {
+    let artifact_url = coordinates.artifact_url(repository);
+    log::info!(
+        "Resolving \"{}:{}:{}\" from \"{}\"...",
+        coordinates.group,
+        coordinates.artifact,
+        coordinates.version,
+        repository
+    );
+
+    let version = match coordinates.version {
+        "LATEST" | "RELEASE" => {
+            let metadata =
+                fetch_metadata(downloader, &format!("{}/maven-metadata.xml", artifact_url)).await?;
+            let versioning = metadata.versioning;
+            let version = if coordinates.version == "LATEST" {
+                versioning.latest.or(versioning.release)
+            } else {
+                versioning.release
+            };
+            version.ok_or_else(|| anyhow!("No {} version was found", coordinates.version))?
+        }
+        version => version.to_string(),
+    };
+
+    let suffix = coordinates
+        .classifier
+        .map(|classifier| format!("-{}", classifier))
+        .unwrap_or_default();
+    let file_version = if version.ends_with("-SNAPSHOT") {
+        snapshot_version(downloader, &artifact_url, &version, coordinates.classifier).await?
+    } else {
+        version.clone()
+    };
+
+    let file_name = format!("{}-{}{}.jar", coordinates.artifact, file_version, suffix);
+    let url = format!("{}/{}/{}", artifact_url, version, file_name);
+
+    // Checksum sidecars are optional, but are verified if published
+    let sha256 = fetch_checksum(downloader, &format!("{}.sha256", url)).await?;
+    let sha1 = match sha256 {
+        Some(_) => None,
+        None => fetch_checksum(downloader, &format!("{}.sha1", url)).await?,
+    };
+
+    Ok(ResolvedDependency {
+        url,
+        file_name: Some(file_name),
+        version: Some(version),
+        sha1,
+        sha256,
+        ..Default::default()
+    })
+}
+
+/// Resolves the timestamped version of a snapshot
+async fn snapshot_version(
+    downloader: &Downloader,
+    artifact_url: &str,
+    version: &str,
+    classifier: Option<&str>,
+) -> anyhow::Result<String> {
+    let metadata = fetch_metadata(
+        downloader,
+        &format!("{}/{}/maven-metadata.xml", artifact_url, version),
+    )
+    .await?;
+    let versioning = metadata.versioning;
+
+    let snapshot_version = versioning.snapshot_versions.and_then(|versions| {
+        versions
+            .snapshot_version
+            .into_iter()
+            .find(|entry| entry.extension == "jar" && entry.classifier.as_deref() == classifier)
+            .map(|entry| entry.value)
+    });
+    if let Some(snapshot_version) = snapshot_version {
+        return Ok(snapshot_version);
+    }
+
+    match versioning.snapshot {
+        Some(Snapshot {
+            timestamp: Some(timestamp),
+            build_number: Some(build_number),
+        }) => Ok(version.replace("SNAPSHOT", &format!("{}-{}", timestamp, build_number))),
+        // Locally deployed snapshots are not timestamped
+        _ => Ok(version.to_string()),
+    }
+}
+
+async fn fetch_metadata(downloader: &Downloader, url: &str) -> anyhow::Result<Metadata> {
+    let contents = downloader.fetch_text(url).await?;
+    quick_xml::de::from_str(&contents)
+        .map_err(|err| anyhow!("Could not parse \"{}\" ({})", url, err))
+}
+
+/// Fetches a checksum sidecar file, `None` if it is not published
+async fn fetch_checksum(downloader: &Downloader, url: &str) -> anyhow::Result<Option<String>> {
+    let contents = downloader.fetch_optional_text(url).await?;
+    Ok(contents.and_then(|contents| {
+        contents
+            .split_whitespace()
+            .next()
+            .map(|checksum| checksum.to_string())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTIFACT_PATH: &str = "/net/luckperms/api";
+
+    async fn repository() -> mockito::ServerGuard {
+        let mut repository = mockito::Server::new_async().await;
+        repository
+            .mock(
+                "GET",
+                format!("{}/maven-metadata.xml", ARTIFACT_PATH).as_str(),
+            )
+            .with_body(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+                <metadata>
+                  <groupId>net.luckperms</groupId>
+                  <artifactId>api</artifactId>
+                  <versioning>
+                    <latest>5.5-SNAPSHOT</latest>
+                    <release>5.4</release>
+                    <versions><version>5.4</version><version>5.5-SNAPSHOT</version></versions>
+                  </versioning>
+                </metadata>"#,
+            )
+            .create_async()
+            .await;
+        repository
+            .mock(
+                "GET",
+                format!("{}/5.5-SNAPSHOT/maven-metadata.xml", ARTIFACT_PATH).as_str(),
+            )
+            .with_body(
+                r#"<metadata>
+                  <versioning>
+                    <snapshot><timestamp>20240101.120000</timestamp><buildNumber>3</buildNumber></snapshot>
+                    <snapshotVersions>
+                      <snapshotVersion><extension>pom</extension><value>5.5-20240101.120000-3</value></snapshotVersion>
+                      <snapshotVersion><classifier>sources</classifier><extension>jar</extension><value>5.5-20240101.110000-2</value></snapshotVersion>
+                      <snapshotVersion><extension>jar</extension><value>5.5-20240101.120000-3</value></snapshotVersion>
+                    </snapshotVersions>
+                  </versioning>
+                </metadata>"#,
+            )
+            .create_async()
+            .await;
+        repository
+            .mock("GET", mockito::Matcher::Regex(r"\.jar\.sha(1|256)$".into()))
+            .with_status(404)
+            .create_async()
+            .await;
+        repository
+    }
+
+    async fn resolve_version(
+        repository: &mockito::ServerGuard,
+        coordinates: &str,
+        version: &str,
+    ) -> anyhow::Result<ResolvedDependency> {
+        let repositories = [Repository {
+            url: repository.url(),
+            username: None,
+            password: None,
+        }];
+        resolve(&Downloader::new(false), coordinates, version, &repositories).await
+    }
+
+    #[tokio::test]
+    async fn resolves_versions_from_the_metadata() {
+        let repository = repository().await;
+
+        let release = resolve_version(&repository, "net.luckperms:api", "latest")
+            .await
+            .unwrap();
+        assert_eq!(release.version.as_deref(), Some("5.4"));
+        assert_eq!(
+            release.url,
+            format!("{}{}/5.4/api-5.4.jar", repository.url(), ARTIFACT_PATH)
+        );
+        assert_eq!(release.sha256, None);
+        assert_eq!(release.sha1, None);
+
+        let latest = resolve_version(&repository, "net.luckperms:api", "LATEST")
+            .await
+            .unwrap();
+        assert_eq!(latest.version.as_deref(), Some("5.5-SNAPSHOT"));
+    }
+
+    #[tokio::test]
+    async fn resolves_timestamped_snapshots() {
+        let repository = repository().await;
+
+        let snapshot = resolve_version(&repository, "net.luckperms:api:5.5-SNAPSHOT", "latest")
+            .await
+            .unwrap();
+        assert_eq!(snapshot.version.as_deref(), Some("5.5-SNAPSHOT"));
+        assert_eq!(
+            snapshot.file_name.as_deref(),
+            Some("api-5.5-20240101.120000-3.jar")
+        );
+        assert_eq!(
+            snapshot.url,
+            format!(
+                "{}{}/5.5-SNAPSHOT/api-5.5-20240101.120000-3.jar",
+                repository.url(),
+                ARTIFACT_PATH
+            )
+        );
+
+        let sources = resolve_version(
+            &repository,
+            "net.luckperms:api:5.5-SNAPSHOT:sources",
+            "latest",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            sources.file_name.as_deref(),
+            Some("api-5.5-20240101.110000-2-sources.jar")
+        );
+    }
+
+    #[tokio::test]
+    async fn verifies_published_checksums() {
+        let mut repository = mockito::Server::new_async().await;
+        repository
+            .mock(
+                "GET",
+                format!("{}/5.3/api-5.3.jar.sha256", ARTIFACT_PATH).as_str(),
+            )
+            .with_body("abc123  api-5.3.jar\n")
+            .create_async()
+            .await;
+
+        let resolved = resolve_version(&repository, "net.luckperms:api", "5.3")
+            .await
+            .unwrap();
+        assert_eq!(resolved.sha256.as_deref(), Some("abc123"));
+        assert_eq!(resolved.sha1, None);
+    }
+}
@@ -0,0 +1,31 @@
+use anyhow::Context;
+use reqwest::header::HeaderMap;
+
+pub(crate) mod git;
+pub(crate) mod github;
+pub(crate) mod hangar;
+pub(crate) mod jenkins;
+pub(crate) mod maven;
+pub(crate) mod modrinth;
+
+/// Checks if a file name matches a pattern,
+/// the pattern is a glob (eg. `*-bukkit.jar`), or a regex if wrapped in slashes (eg. `/^spark-.*\.jar$/`)
+pub(crate) fn matches_pattern(pattern: &str, name: &str) -> anyhow::Result<bool> {
+    if let Some(regex) = pattern
+        .strip_prefix('/')
+        .and_then(|pattern| pattern.strip_suffix('/'))
+    {
+        let regex =
+            regex::Regex::new(regex).with_context(|| format!("Invalid pattern \"{}\"", pattern))?;
+        return Ok(regex.is_match(name));
+    }
+
+    let glob =
+        glob::Pattern::new(pattern).with_context(|| format!("Invalid pattern \"{}\"", pattern))?;
+    Ok(glob.matches(name))
+}
+
+/// Extra headers required by a source to request a URL (eg. authentication)
+pub(crate) fn request_headers(url: &str) -> HeaderMap {
+    github::request_headers(url)
+}
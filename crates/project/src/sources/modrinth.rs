@@ -0,0 +1,217 @@
+use std::env;
+
+use anyhow::anyhow;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::dependencies::{DependencyType, ResolvedDependency};
+use crate::installer::Downloader;
+use crate::Server;
+
+const MODRINTH_API_URL: &str = "https://api.modrinth.com";
+
+#[derive(Deserialize, Debug)]
+struct Version {
+    id: String,
+    version_number: String,
+    files: Vec<VersionFile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct VersionFile {
+    url: String,
+    filename: String,
+    #[serde(default)]
+    primary: bool,
+    hashes: FileHashes,
+}
+
+#[derive(Deserialize, Debug)]
+struct FileHashes {
+    sha1: Option<String>,
+    sha512: Option<String>,
+}
+
+/// Base URL of the Modrinth API, can be overridden with the `CRAFTY_MODRINTH_API_URL` variable
+fn api_url() -> String {
+    env::var("CRAFTY_MODRINTH_API_URL")
+        .unwrap_or_else(|_| MODRINTH_API_URL.into())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Modrinth loaders that can run on a server brand, in order of preference
+pub(crate) fn loaders(server: &Server, dependency_type: &DependencyType) -> Vec<&'static str> {
+    match dependency_type {
+        DependencyType::DataPack => return vec!["datapack"],
+        DependencyType::ResourcePack => return vec!["minecraft"],
+        _ => {}
+    }
+
+    let brand = server.brand.as_deref().unwrap_or_default().to_lowercase();
+    match brand.as_str() {
+        "paper" => vec!["paper", "spigot", "bukkit"],
+        "purpur" => vec!["purpur", "paper", "spigot", "bukkit"],
+        "folia" => vec!["folia"],
+        "spigot" => vec!["spigot", "bukkit"],
+        "bukkit" => vec!["bukkit"],
+        "velocity" => vec!["velocity"],
+        "waterfall" => vec!["waterfall", "bungeecord"],
+        "bungeecord" => vec!["bungeecord"],
+        "fabric" => vec!["fabric"],
+        "quilt" => vec!["quilt", "fabric"],
+        "forge" => vec!["forge"],
+        "neoforge" => vec!["neoforge"],
+        _ => vec![],
+    }
+}
+
+/// Resolves a version of a Modrinth project compatible with the server version and brand,
+/// `latest` can be used to resolve the latest compatible version
+pub(crate) async fn resolve(
+    downloader: &Downloader,
+    slug: &str,
+    version: &str,
+    dependency_type: &DependencyType,
+    server: &Server,
+) -> anyhow::Result<ResolvedDependency> {
+    resolve_from(
+        downloader,
+        &api_url(),
+        slug,
+        version,
+        dependency_type,
+        server,
+    )
+    .await
+}
+
+async fn resolve_from(
+    downloader: &Downloader,
+    api_url: &str,
+    slug: &str,
+    version: &str,
+    dependency_type: &DependencyType,
+    server: &Server,
+) -> anyhow::Result<ResolvedDependency> {
+    log::info!("Resolving \"{}\" {} from Modrinth...", slug, version);
+
+    let mut params = vec![];
+    let loaders = loaders(server, dependency_type);
+    if !loaders.is_empty() {
+        params.push(("loaders", serde_json::to_string(&loaders)?));
+    }
+    if let Some(game_version) = server
+        .version
+        .as_deref()
+        .filter(|version| !version.is_empty())
+    {
+        params.push(("game_versions", serde_json::to_string(&[game_version])?));
+    }
+
+    let url = Url::parse_with_params(&format!("{}/v2/project/{}/version", api_url, slug), &params)?;
+    let versions = downloader.fetch_json::<Vec<Version>>(url.as_str()).await?;
+
+    // Versions are sorted from newest to oldest
+    let found = versions
+        .into_iter()
+        .find(|entry| version == "latest" || entry.version_number == version || entry.id == version)
+        .ok_or_else(|| {
+            anyhow!(
+                "Could not find version \"{}\" of \"{}\" compatible with {} {} on Modrinth",
+                version,
+                slug,
+                server.brand.as_deref().unwrap_or("the server"),
+                server.version.as_deref().unwrap_or_default()
+            )
+        })?;
+
+    let mut files = found.files;
+    if files.is_empty() {
+        return Err(anyhow!(
+            "Version \"{}\" of \"{}\" has no files",
+            version,
+            slug
+        ));
+    }
+    let index = files.iter().position(|file| file.primary).unwrap_or(0);
+    let file = files.swap_remove(index);
+
+    Ok(ResolvedDependency {
+        url: file.url,
+        file_name: Some(file.filename),
+        version: Some(found.version_number),
+        sha1: file.hashes.sha1,
+        sha512: file.hashes.sha512,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(yaml: &str) -> Server {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn picks_loaders_from_the_brand_and_type() {
+        let paper = server("brand: Paper\nversion: 1.20.4");
+        assert_eq!(
+            loaders(&paper, &DependencyType::Plugin),
+            vec!["paper", "spigot", "bukkit"]
+        );
+        assert_eq!(loaders(&paper, &DependencyType::DataPack), vec!["datapack"]);
+        assert!(loaders(&server("source: server.jar"), &DependencyType::Plugin).is_empty());
+    }
+
+    #[tokio::test]
+    async fn resolves_compatible_versions() {
+        let mut api = mockito::Server::new_async().await;
+        let versions = api
+            .mock("GET", "/v2/project/luckperms/version")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("loaders".into(), r#"["fabric"]"#.into()),
+                mockito::Matcher::UrlEncoded("game_versions".into(), r#"["1.20.4"]"#.into()),
+            ]))
+            .with_body(
+                r#"[
+                    {"id": "b", "version_number": "5.4.1", "files": [
+                        {"url": "https://cdn/sources.jar", "filename": "sources.jar", "hashes": {}},
+                        {"url": "https://cdn/lp-5.4.1.jar", "filename": "lp-5.4.1.jar", "primary": true, "hashes": {"sha1": "aa"}}
+                    ]},
+                    {"id": "a", "version_number": "5.4.0", "files": [
+                        {"url": "https://cdn/lp-5.4.0.jar", "filename": "lp-5.4.0.jar", "hashes": {}}
+                    ]}
+                ]"#,
+            )
+            .expect(3)
+            .create_async()
+            .await;
+        let server = server("brand: fabric\nversion: 1.20.4");
+        let downloader = Downloader::new(false);
+        let api_url = api.url();
+        let resolve = |version| {
+            resolve_from(
+                &downloader,
+                &api_url,
+                "luckperms",
+                version,
+                &DependencyType::Mod,
+                &server,
+            )
+        };
+
+        let latest = resolve("latest").await.unwrap();
+        assert_eq!(latest.version.as_deref(), Some("5.4.1"));
+        assert_eq!(latest.url, "https://cdn/lp-5.4.1.jar");
+        assert_eq!(latest.sha1.as_deref(), Some("aa"));
+
+        let pinned = resolve("5.4.0").await.unwrap();
+        assert_eq!(pinned.file_name.as_deref(), Some("lp-5.4.0.jar"));
+
+        assert!(resolve("4.0.0").await.is_err());
+        versions.assert_async().await;
+    }
+}
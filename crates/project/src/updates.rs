@@ -0,0 +1,278 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+
+use crate::installer::Downloader;
+use crate::lockfile::Lockfile;
+use crate::providers::ResolvedServer;
+use crate::{
+    dependencies, editor, load_project, providers, InstallOptions, Project, ProjectMetadata,
+};
+
+/// Versions of the server or a dependency, `None` if they are unknown or cannot be checked
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct OutdatedEntry {
+    pub id: String,
+    pub current: Option<String>,
+    /// Latest version compatible with the server `version`
+    pub latest_compatible: Option<String>,
+    pub latest: Option<String>,
+    /// Whether a newer compatible version is available
+    pub outdated: bool,
+}
+
+impl OutdatedEntry {
+    fn new(
+        id: &str,
+        current: Option<String>,
+        latest: Option<String>,
+        latest_compatible: Option<String>,
+    ) -> Self {
+        let outdated = latest_compatible.is_some() && latest_compatible != current;
+
+        Self {
+            id: id.to_string(),
+            current,
+            latest_compatible,
+            latest,
+            outdated,
+        }
+    }
+}
+
+/// Prints the current, latest compatible and latest versions of the server and each dependency
+pub async fn outdated(root_directory: PathBuf, json: bool) -> anyhow::Result<()> {
+    let project = load_project(root_directory)?;
+    let lockfile = Lockfile::load(&project.root_directory)?.unwrap_or_default();
+    let downloader = Downloader::for_project(&project.project_details, &InstallOptions::default())?;
+    let entries = outdated_entries(&project.project_details, &lockfile, &downloader).await;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    let header = ["Name", "Current", "Compatible", "Latest"].map(String::from);
+    let rows: Vec<[String; 4]> = entries
+        .iter()
+        .map(|entry| {
+            let display = |version: &Option<String>| version.clone().unwrap_or("-".into());
+            [
+                entry.id.clone(),
+                display(&entry.current),
+                display(&entry.latest_compatible),
+                display(&entry.latest),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|column| column.len());
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.len());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(column, width)| format!("{:width$}", column, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+
+    Ok(())
+}
+
+async fn outdated_entries(
+    project: &ProjectMetadata,
+    lockfile: &Lockfile,
+    downloader: &Downloader,
+) -> Vec<OutdatedEntry> {
+    let server = &project.server;
+    let mut entries = vec![];
+
+    let current = lockfile.locked_server(server).or_else(|| {
+        server.version.clone().map(|version| ResolvedServer {
+            version,
+            build: server.build,
+            loader_version: server.loader_version.clone(),
+            ..Default::default()
+        })
+    });
+    let (latest, compatible) = providers::latest_servers(server, downloader)
+        .await
+        .unwrap_or_else(|err| {
+            log::warn!("Could not check the server for updates ({})", err);
+            (None, None)
+        });
+    let mut entry = OutdatedEntry::new(
+        "server",
+        current.as_ref().map(ResolvedServer::display_version),
+        latest.map(|resolved| resolved.display_version()),
+        compatible.as_ref().map(ResolvedServer::display_version),
+    );
+    entry.outdated = match (&current, &compatible) {
+        (Some(current), Some(compatible)) => is_server_outdated(current, compatible),
+        (None, compatible) => compatible.is_some(),
+        (_, None) => false,
+    };
+    entries.push(entry);
+
+    let mut ids: Vec<&String> = project.dependencies.keys().collect();
+    ids.sort();
+
+    for id in ids {
+        let dependency = &project.dependencies[id];
+        let current = lockfile
+            .locked_dependency(id, dependency)
+            .and_then(|resolved| resolved.version)
+            .or(dependency.version.clone())
+            .filter(|version| version != "latest");
+
+        let (latest, compatible) =
+            dependencies::latest_versions(id, dependency, project, downloader)
+                .await
+                .unwrap_or_else(|err| {
+                    log::warn!("Could not check {} for updates ({})", id, err);
+                    (None, None)
+                });
+        entries.push(OutdatedEntry::new(id, current, latest, compatible));
+    }
+
+    entries
+}
+
+/// Whether a newer compatible server is available, the build and loader are only compared if they are known
+/// (pinned or locked), as the latest ones are installed otherwise
+fn is_server_outdated(current: &ResolvedServer, compatible: &ResolvedServer) -> bool {
+    current.version != compatible.version
+        || (current.build.is_some() && current.build != compatible.build)
+        || (current.loader_version.is_some() && current.loader_version != compatible.loader_version)
+}
+
+/// Bumps the pinned versions of the given entries (dependency ids or `server`, all if empty)
+/// to their latest compatible version in `crafty.yml`, then installs them again
+pub async fn update(root_directory: PathBuf, ids: Vec<String>) -> anyhow::Result<()> {
+    let project = load_project(root_directory)?;
+    let details = &project.project_details;
+    let crafty_file = project.root_directory.join("crafty.yml");
+
+    if let Some(id) = ids
+        .iter()
+        .find(|id| *id != "server" && !details.dependencies.contains_key(*id))
+    {
+        return Err(anyhow!(
+            "The dependency \"{}\" was not found in \"crafty.yml\"",
+            id
+        ));
+    }
+    let options = InstallOptions {
+        update: Some(ids),
+        ..Default::default()
+    };
+    let downloader = Downloader::for_project(details, &options)?;
+
+    let mut contents = fs::read_to_string(&crafty_file)?;
+    let original = contents.clone();
+
+    let server = &details.server;
+    if options.should_update("server")
+        && (server.build.is_some() || server.loader_version.is_some())
+    {
+        if let (_, Some(compatible)) = providers::latest_servers(server, &downloader).await? {
+            if let (Some(pinned), Some(build)) = (server.build, compatible.build) {
+                if pinned != build {
+                    log::info!("Updating server build from {} to {}", pinned, build);
+                    contents =
+                        editor::set_property(&contents, &["server", "build"], &build.into())?;
+                }
+            }
+
+            if let (Some(pinned), Some(loader_version)) =
+                (&server.loader_version, compatible.loader_version)
+            {
+                if *pinned != loader_version {
+                    log::info!(
+                        "Updating server loader from {} to {}",
+                        pinned,
+                        loader_version
+                    );
+                    contents = editor::set_property(
+                        &contents,
+                        &["server", "loader-version"],
+                        &loader_version.into(),
+                    )?;
+                }
+            }
+        }
+    }
+
+    let mut ids: Vec<&String> = details.dependencies.keys().collect();
+    ids.sort();
+
+    for id in ids {
+        let dependency = &details.dependencies[id];
+        let pinned = match dependency.version.as_deref() {
+            Some(version) if version != "latest" && options.should_update(id) => version,
+            _ => continue,
+        };
+
+        if let (_, Some(compatible)) =
+            dependencies::latest_versions(id, dependency, details, &downloader).await?
+        {
+            if compatible != pinned {
+                log::info!("Updating {} from {} to {}", id, pinned, compatible);
+                contents = editor::set_property(
+                    &contents,
+                    &["dependencies", id, "version"],
+                    &compatible.into(),
+                )?;
+            }
+        }
+    }
+
+    if contents == original {
+        return crate::install_project(&project, &options, &downloader).await;
+    }
+
+    // The new versions are saved once they are installed, so a failed install keeps the project as it was
+    let updated = serde_yaml::from_str::<ProjectMetadata>(&contents)
+        .context("Could not update \"crafty.yml\"")?;
+    let updated = Project::new(&project.root_directory, updated);
+    crate::install_project(&updated, &options, &downloader).await?;
+    fs::write(&crafty_file, contents).context("Could not save \"crafty.yml\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(version: &str, build: Option<u32>) -> ResolvedServer {
+        ResolvedServer {
+            version: version.into(),
+            build,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn compares_servers_by_version_and_known_build() {
+        let compatible = server("1.20.4", Some(463));
+
+        assert!(!is_server_outdated(&server("1.20.4", None), &compatible));
+        assert!(!is_server_outdated(
+            &server("1.20.4", Some(463)),
+            &compatible
+        ));
+        assert!(is_server_outdated(
+            &server("1.20.4", Some(400)),
+            &compatible
+        ));
+        assert!(is_server_outdated(&server("1.20.2", None), &compatible));
+    }
+}